@@ -0,0 +1,377 @@
+// Security events for a SIEM: RFC 5424 syslog over UDP or TCP, payloads hashed
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::clock::{Clock, SystemClock};
+use crate::RedirectError;
+
+// RFC 5424 facility 4 (security/authorization) at severity 4 (warning)
+const PRIORITY: u8 = 4 * 8 + 4;
+// Private enterprise number reserved for documentation (RFC 5612), used for the structured-data id
+const SD_ID: &str = "event@32473";
+
+/// What kind of security event happened; names match the crate's tracing targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditKind {
+    BlockedRedirect,
+    FlaggedQuery,
+    AuthFailure,
+}
+
+impl AuditKind {
+    pub fn category(self) -> &'static str {
+        match self {
+            Self::BlockedRedirect => "redirect",
+            Self::FlaggedQuery => "sqli",
+            Self::AuthFailure => "auth",
+        }
+    }
+
+    // Syslog MSGID
+    fn msg_id(self) -> &'static str {
+        match self {
+            Self::BlockedRedirect => "redirect_blocked",
+            Self::FlaggedQuery => "query_flagged",
+            Self::AuthFailure => "auth_failed",
+        }
+    }
+
+    // Free-form MSG part
+    fn description(self) -> &'static str {
+        match self {
+            Self::BlockedRedirect => "Blocked redirect",
+            Self::FlaggedQuery => "Flagged query",
+            Self::AuthFailure => "Authentication failed",
+        }
+    }
+}
+
+/// One security event. `payload` is the attacker-controlled input (redirect target,
+/// query, ...); sinks must only ever export its hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    pub kind: AuditKind,
+    pub payload: String,
+    pub reason: String,
+}
+
+impl AuditEvent {
+    pub fn blocked_redirect(target: &str, reason: &RedirectError) -> Self {
+        Self { kind: AuditKind::BlockedRedirect, payload: target.to_string(), reason: reason.to_string() }
+    }
+
+    /// Hex SHA-256 of the payload: lets analysts correlate repeats without seeing the input
+    pub fn payload_sha256(&self) -> String {
+        Sha256::digest(self.payload.as_bytes()).iter().fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{:02x}", b);
+            hex
+        })
+    }
+}
+
+/// Destination for security events. `redirect_guard` reports blocked redirects to one
+/// when the app registers it as `web::Data<dyn AuditSink>`
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent);
+}
+
+/// Events waiting for the syslog thread; past this, new events are dropped, not queued
+pub const SYSLOG_QUEUE_CAPACITY: usize = 1024;
+
+// A collector that stops reading must not hold the sender thread (or the queue) forever
+const SEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+enum Transport {
+    Udp(UdpSocket),
+    Tcp { collector: SocketAddr, stream: Option<TcpStream> },
+}
+
+impl Transport {
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        match self {
+            Self::Udp(socket) => socket.send(message.as_bytes()).map(drop),
+            Self::Tcp { collector, stream } => {
+                if stream.is_none() {
+                    *stream = Some(connect_tcp(*collector)?); // Reconnect after an earlier failure
+                }
+                let conn = stream.as_mut().expect("connected above");
+                let sent = write!(conn, "{} {}", message.len(), message).and_then(|_| conn.flush());
+                if sent.is_err() {
+                    *stream = None; // Half-written frame: start over on a fresh connection
+                }
+                sent
+            }
+        }
+    }
+}
+
+fn connect_tcp(collector: SocketAddr) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&collector, SEND_TIMEOUT)?;
+    stream.set_write_timeout(Some(SEND_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Sends each event as one RFC 5424 message to a syslog collector.
+/// UDP sends one datagram per event; TCP uses octet-counted framing (RFC 6587).
+/// `record` only formats the message and queues it: a background thread does the network
+/// I/O with a write timeout, reconnecting TCP after errors. Attackers choose when events
+/// fire, so a slow collector must never stall request handling; when the queue is full,
+/// events are dropped and counted (`dropped_events`). Delivery failures are logged, never
+/// surfaced to the request
+#[derive(Debug)]
+pub struct SyslogSink<C: Clock = SystemClock> {
+    queue: SyncSender<String>,
+    dropped: Arc<AtomicU64>,
+    hostname: String,
+    app_name: String,
+    clock: C,
+}
+
+impl SyslogSink {
+    pub fn udp(collector: impl ToSocketAddrs) -> io::Result<Self> {
+        let collector = collector
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address for syslog collector"))?;
+        let local: SocketAddr = if collector.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(collector)?;
+        socket.set_write_timeout(Some(SEND_TIMEOUT))?;
+        Self::with_transport(Transport::Udp(socket))
+    }
+
+    /// Connects once up front, so a wrong address fails here rather than silently later
+    pub fn tcp(collector: impl ToSocketAddrs) -> io::Result<Self> {
+        let collector = collector
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address for syslog collector"))?;
+        let stream = connect_tcp(collector)?;
+        Self::with_transport(Transport::Tcp { collector, stream: Some(stream) })
+    }
+
+    fn with_transport(mut transport: Transport) -> io::Result<Self> {
+        let (queue, messages) = sync_channel::<String>(SYSLOG_QUEUE_CAPACITY);
+        // Ends when the sink (the only sender) is dropped
+        thread::Builder::new().name("syslog-sink".into()).spawn(move || {
+            for message in messages {
+                if let Err(e) = transport.send(&message) {
+                    tracing::warn!(target: "redirect", error = %e, "Failed to send audit event to syslog");
+                }
+            }
+        })?;
+        Ok(Self {
+            queue,
+            dropped: Arc::default(),
+            hostname: "-".into(),
+            app_name: "open_redirects".into(),
+            clock: SystemClock,
+        })
+    }
+}
+
+impl<C: Clock> SyslogSink<C> {
+    /// Same sink with another time source (tests)
+    pub fn with_clock<D: Clock>(self, clock: D) -> SyslogSink<D> {
+        let Self { queue, dropped, hostname, app_name, .. } = self;
+        SyslogSink { queue, dropped, hostname, app_name, clock }
+    }
+
+    /// HOSTNAME field; "-" (unknown) by default
+    pub fn hostname(mut self, hostname: &str) -> Self {
+        self.hostname = header_field(hostname, 255);
+        self
+    }
+
+    /// APP-NAME field; "open_redirects" by default
+    pub fn app_name(mut self, app_name: &str) -> Self {
+        self.app_name = header_field(app_name, 48);
+        self
+    }
+
+    /// Events dropped so far because the queue was full
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// The RFC 5424 line for `event`, without transport framing
+    pub fn format(&self, event: &AuditEvent) -> String {
+        format!(
+            "<{}>1 {} {} {} {} {} [{} category=\"{}\" reason=\"{}\" payload_sha256=\"{}\"] {}",
+            PRIORITY,
+            rfc3339_utc(self.clock.unix_secs()),
+            self.hostname,
+            self.app_name,
+            std::process::id(),
+            event.kind.msg_id(),
+            SD_ID,
+            event.kind.category(),
+            escape_param(&event.reason),
+            event.payload_sha256(),
+            event.kind.description(),
+        )
+    }
+}
+
+impl<C: Clock> AuditSink for SyslogSink<C> {
+    fn record(&self, event: &AuditEvent) {
+        match self.queue.try_send(self.format(event)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                // Warn at 1, 2, 4, 8, ... drops: a flood must not turn into a log flood
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped.is_power_of_two() {
+                    tracing::warn!(target: "redirect", dropped, "Syslog queue full, dropping audit events");
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                tracing::warn!(target: "redirect", category = event.kind.category(), "Syslog sender thread is gone");
+            }
+        }
+    }
+}
+
+// Header fields are printable ASCII without spaces; anything else becomes '_'
+fn header_field(value: &str, max_len: usize) -> String {
+    let cleaned: String =
+        value.chars().take(max_len).map(|c| if c.is_ascii_graphic() { c } else { '_' }).collect();
+    if cleaned.is_empty() { "-".into() } else { cleaned }
+}
+
+// PARAM-VALUE escaping: '"', '\' and ']' are backslash-escaped; control characters dropped
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars().filter(|c| !c.is_control()) {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// 2023-11-14T22:13:20Z from Unix seconds (civil-from-days, proleptic Gregorian)
+fn rfc3339_utc(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, secs / 3_600, secs / 60 % 60, secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use actix_web::{web, App, HttpResponse, http::StatusCode, middleware::from_fn};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_timestamp_and_escaping() {
+        // Known dates format correctly; SD-PARAM specials are escaped.
+        assert_eq!(rfc3339_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339_utc(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(rfc3339_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(escape_param("a\"b]c\\d\ne"), "a\\\"b\\]c\\\\de");
+        assert_eq!(header_field("web 01", 255), "web_01");
+    }
+
+    #[test]
+    fn test_full_queue_drops_instead_of_blocking() {
+        // Nobody drains this queue: the first event waits in it, the rest are counted and dropped.
+        let (queue, pending) = sync_channel(1);
+        let sink =
+            SyslogSink { queue, dropped: Arc::default(), hostname: "-".into(), app_name: "x".into(), clock: SystemClock };
+        let event = AuditEvent::blocked_redirect("https://evil.com/", &RedirectError::UntrustedDomain);
+        for _ in 0..3 {
+            sink.record(&event);
+        }
+        assert_eq!(sink.dropped_events(), 2);
+        assert!(pending.try_recv().unwrap().ends_with("Blocked redirect"));
+    }
+
+    #[test]
+    fn test_tcp_reconnects_after_collector_hangs_up() {
+        // The collector closes the first connection; later events arrive on a new one.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink = SyslogSink::tcp(listener.local_addr().unwrap()).unwrap();
+        let event = AuditEvent::blocked_redirect("https://evil.com/", &RedirectError::UntrustedDomain);
+        drop(listener.accept().unwrap()); // Hang up on the first connection
+
+        listener.set_nonblocking(true).unwrap();
+        let mut second = None;
+        for _ in 0..200 {
+            sink.record(&event); // The first writes after the hang-up fail; one of them triggers the reconnect
+            if let Ok((stream, _)) = listener.accept() {
+                second = Some(stream);
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let mut second = second.expect("sink never reconnected");
+        second.set_nonblocking(false).unwrap();
+        second.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let mut buf = [0u8; 512];
+        let len = std::io::Read::read(&mut second, &mut buf).unwrap();
+        assert!(std::str::from_utf8(&buf[..len]).unwrap().contains("redirect_blocked"));
+    }
+
+    #[actix_web::test]
+    async fn test_blocked_redirect_sent_as_syslog() {
+        // The guard reports the block; the datagram is RFC 5424 and carries only the target's hash.
+        let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+        collector.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let sink = SyslogSink::udp(collector.local_addr().unwrap())
+            .unwrap()
+            .with_clock(MockClock::default())
+            .hostname("web-1");
+        let sink: Arc<dyn AuditSink> = Arc::new(sink);
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::from(sink))
+                .wrap(from_fn(crate::redirect_guard))
+                .route("/login", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/login?redirect=https://evil.com/phish").to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+        let mut buf = [0u8; 1024];
+        let len = collector.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..len]).unwrap();
+        let event = AuditEvent::blocked_redirect("https://evil.com/phish", &RedirectError::UntrustedDomain);
+        let hash = event.payload_sha256();
+        assert_eq!(
+            message,
+            format!(
+                "<36>1 2023-11-14T22:13:20Z web-1 open_redirects {} redirect_blocked \
+                 [event@32473 category=\"redirect\" reason=\"Domain not in allow-list\" \
+                 payload_sha256=\"{}\"] Blocked redirect",
+                std::process::id(),
+                hash
+            )
+        );
+        assert!(!message.contains("evil.com"));
+    }
+}
@@ -0,0 +1,145 @@
+// Bearer-token authentication for admin endpoints
+use std::sync::Arc;
+
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+    body::{MessageBody, BoxBody},
+    http::header,
+    middleware::Next
+};
+use secrecy::{ExposeSecret, SecretString};
+use subtle::ConstantTimeEq;
+
+/// Middleware state holding the expected bearer token.
+/// The token stays wrapped in a `SecretString` so it never shows up in logs.
+#[derive(Clone)]
+pub struct BearerAuth {
+    token: Arc<SecretString>,
+}
+
+impl BearerAuth {
+    pub fn new(token: SecretString) -> Self {
+        Self { token: Arc::new(token) }
+    }
+
+    /// Checks an `Authorization` header value against the configured token.
+    /// Comparison is constant-time so response timing doesn't leak a matching prefix.
+    /// The scheme is case-insensitive (RFC 7235); an empty configured token authorizes nothing.
+    pub fn is_authorized(&self, header_value: Option<&str>) -> bool {
+        let expected = self.token.expose_secret();
+        if expected.is_empty() {
+            return false; // A blank secret (e.g. an unset env var) must not accept "Bearer "
+        }
+        let presented = header_value
+            .and_then(|v| v.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+            .map(|(_, token)| token.trim_start());
+        match presented {
+            Some(presented) => presented.as_bytes().ct_eq(expected.as_bytes()).into(),
+            None => false,
+        }
+    }
+
+    /// Middleware body: forwards authorized requests, answers 401 otherwise.
+    /// Wrap with `from_fn(move |req, next| auth.clone().guard(req, next))`.
+    pub async fn guard(
+        self,
+        req: ServiceRequest,
+        next: Next<impl MessageBody + 'static>,
+    ) -> Result<ServiceResponse<BoxBody>, Error> {
+        let presented = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok());
+
+        if self.is_authorized(presented) {
+            next.call(req).await.map(|res| res.map_into_boxed_body())
+        } else {
+            // Challenge the client per RFC 6750 without revealing why it failed
+            tracing::warn!(target: "auth", path = req.path(), "Rejected request without a valid bearer token");
+            let response = HttpResponse::Unauthorized()
+                .append_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .finish()
+                .map_into_boxed_body();
+            Ok(req.into_response(response))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, middleware::from_fn, http::StatusCode};
+
+    fn admin_app_auth() -> BearerAuth {
+        BearerAuth::new(SecretString::from("s3cret-admin-token"))
+    }
+
+    #[actix_web::test]
+    async fn test_scheme_case_insensitive_and_empty_secret_refused() {
+        // "bearer" matches like "Bearer"; a blank configured token accepts no header at all.
+        let auth = admin_app_auth();
+        assert!(auth.is_authorized(Some("bearer s3cret-admin-token")));
+        assert!(auth.is_authorized(Some("BEARER s3cret-admin-token")));
+        assert!(!auth.is_authorized(Some("Basic s3cret-admin-token")));
+
+        let blank = BearerAuth::new(SecretString::from(""));
+        assert!(!blank.is_authorized(Some("Bearer ")));
+        assert!(!blank.is_authorized(Some("Bearer")));
+        assert!(!blank.is_authorized(None));
+    }
+
+    #[actix_web::test]
+    async fn test_correct_token_passes() {
+        // A matching bearer token reaches the handler.
+        let auth = admin_app_auth();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| auth.clone().guard(req, next)))
+                .route("/admin/reload", web::post().to(|| async { "reloaded" })),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/admin/reload")
+            .insert_header((header::AUTHORIZATION, "Bearer s3cret-admin-token"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_token_is_unauthorized() {
+        // A wrong token gets a 401 with a Bearer challenge.
+        let auth = admin_app_auth();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| auth.clone().guard(req, next)))
+                .route("/admin/reload", web::post().to(|| async { "reloaded" })),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/admin/reload")
+            .insert_header((header::AUTHORIZATION, "Bearer guessed-token"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get(header::WWW_AUTHENTICATE).unwrap(), "Bearer");
+    }
+
+    #[actix_web::test]
+    async fn test_missing_header_is_unauthorized() {
+        // No Authorization header at all is rejected the same way.
+        let auth = admin_app_auth();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| auth.clone().guard(req, next)))
+                .route("/admin/reload", web::post().to(|| async { "reloaded" })),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/admin/reload").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get(header::WWW_AUTHENTICATE).unwrap(), "Bearer");
+    }
+}
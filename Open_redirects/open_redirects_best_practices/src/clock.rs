@@ -0,0 +1,78 @@
+// Time source for expiring tokens, so tests can move time instead of sleeping
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Wall-clock abstraction taken by every time-dependent type in this crate
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// Whole seconds since the Unix epoch (0 if the clock is before it)
+    fn unix_secs(&self) -> u64 {
+        self.now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+}
+
+/// The real system clock; the default everywhere
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Manually driven clock for tests. Clones share the same time, so a test can
+/// keep one handle and advance the clock a token store is holding.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl MockClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self { now: Arc::new(Mutex::new(start)) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+
+    pub fn set(&self, to: SystemTime) {
+        *self.now.lock().unwrap() = to;
+    }
+}
+
+impl Default for MockClock {
+    // Fixed, arbitrary start time so test output is reproducible
+    fn default() -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_advances_shared_time() {
+        // Clones observe every advance; no real time passes.
+        let clock = MockClock::default();
+        let handle = clock.clone();
+        let start = clock.unix_secs();
+        handle.advance(Duration::from_secs(90));
+        assert_eq!(clock.unix_secs(), start + 90);
+    }
+}
@@ -0,0 +1,149 @@
+// Ops tool: look for dangling allow-list hosts that could be taken over
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, ToSocketAddrs};
+
+use crate::RedirectPolicy;
+
+// CNAME targets that a stranger can claim once the original resource is deleted
+const TAKEOVER_PRONE_SUFFIXES: [&str; 10] = [
+    "s3.amazonaws.com", "cloudfront.net", "elasticbeanstalk.com", "herokuapp.com", "herokudns.com",
+    "azurewebsites.net", "cloudapp.net", "trafficmanager.net", "blob.core.windows.net", "github.io",
+];
+
+/// One DNS lookup result
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DnsAnswer {
+    pub cnames: Vec<String>, // Alias chain, if the resolver reports it
+    pub addrs: Vec<IpAddr>,
+}
+
+/// Name resolution used by `audit_allowlist_dns_with`; swap in a mock for tests
+/// or a full DNS client (e.g. hickory) to see CNAME chains
+pub trait DnsResolver {
+    fn resolve(&self, host: &str) -> impl Future<Output = io::Result<DnsAnswer>> + Send;
+}
+
+/// The OS resolver via `getaddrinfo`; reports addresses only, never CNAMEs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl DnsResolver for SystemResolver {
+    async fn resolve(&self, host: &str) -> io::Result<DnsAnswer> {
+        let host = host.to_string();
+        let addrs = actix_web::rt::task::spawn_blocking(move || (host.as_str(), 443).to_socket_addrs())
+            .await
+            .map_err(io::Error::other)??;
+        Ok(DnsAnswer { cnames: Vec::new(), addrs: addrs.map(|a| a.ip()).collect() })
+    }
+}
+
+/// Verdict for one allow-listed host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsAuditStatus {
+    Ok,
+    Unresolvable(String),        // NXDOMAIN, no records or lookup error: likely dangling
+    TakeoverProne(String),       // Aliased to a claimable cloud resource (the CNAME given)
+    NonPublicAddress(IpAddr),    // Public redirect target resolving to private/loopback space
+}
+
+/// Per-host audit result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsAuditEntry {
+    pub host: String,
+    pub status: DnsAuditStatus,
+}
+
+impl DnsAuditEntry {
+    pub fn is_ok(&self) -> bool {
+        self.status == DnsAuditStatus::Ok
+    }
+}
+
+/// Resolves every allow-listed host with the system resolver and reports each one.
+/// Meant for scheduled security checks, not the request path.
+pub async fn audit_allowlist_dns(policy: &RedirectPolicy) -> Vec<DnsAuditEntry> {
+    audit_allowlist_dns_with(policy, &SystemResolver).await
+}
+
+/// `audit_allowlist_dns` with an explicit resolver
+pub async fn audit_allowlist_dns_with(policy: &RedirectPolicy, resolver: &impl DnsResolver) -> Vec<DnsAuditEntry> {
+    let mut entries = Vec::new();
+    for host in policy.hosts() {
+        let status = match resolver.resolve(host).await {
+            Err(e) => DnsAuditStatus::Unresolvable(e.to_string()),
+            Ok(answer) => classify(&answer),
+        };
+        if status != DnsAuditStatus::Ok {
+            tracing::warn!(target: "redirect", host, ?status, "Allow-listed host failed DNS audit");
+        }
+        entries.push(DnsAuditEntry { host: host.to_string(), status });
+    }
+    entries
+}
+
+fn classify(answer: &DnsAnswer) -> DnsAuditStatus {
+    if let Some(cname) = answer.cnames.iter().find(|c| is_takeover_prone(c)) {
+        return DnsAuditStatus::TakeoverProne(cname.clone());
+    }
+    if answer.addrs.is_empty() {
+        return DnsAuditStatus::Unresolvable("no addresses".into());
+    }
+    match answer.addrs.iter().find(|ip| !is_public(ip)) {
+        Some(ip) => DnsAuditStatus::NonPublicAddress(*ip),
+        None => DnsAuditStatus::Ok,
+    }
+}
+
+fn is_takeover_prone(cname: &str) -> bool {
+    let cname = cname.trim_end_matches('.').to_ascii_lowercase();
+    TAKEOVER_PRONE_SUFFIXES
+        .iter()
+        .any(|suffix| cname == *suffix || cname.ends_with(&format!(".{}", suffix)))
+}
+
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified()),
+        IpAddr::V6(v6) => !(v6.is_loopback() || v6.is_unspecified() || (v6.segments()[0] & 0xfe00) == 0xfc00),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
+
+    // Answers from a fixed table; unknown hosts fail like NXDOMAIN
+    struct MockResolver(HashMap<&'static str, DnsAnswer>);
+
+    impl DnsResolver for MockResolver {
+        async fn resolve(&self, host: &str) -> io::Result<DnsAnswer> {
+            self.0
+                .get(host)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "NXDOMAIN"))
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_dangling_and_healthy_hosts() {
+        // A non-resolving host is flagged, a resolving one passes, and risky targets are named.
+        let public = DnsAnswer { cnames: vec![], addrs: vec![IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34))] };
+        let resolver = MockResolver(HashMap::from([
+            ("trusted.com", public.clone()),
+            ("docs.trusted.com", DnsAnswer { cnames: vec!["trusted-docs.s3.amazonaws.com.".into()], ..public.clone() }),
+            ("intranet.trusted.com", DnsAnswer { cnames: vec![], addrs: vec![IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))] }),
+        ]));
+        let policy = RedirectPolicy::new(["trusted.com", "*.trusted.com", "docs.trusted.com", "intranet.trusted.com", "old.trusted.com"]);
+
+        let report = audit_allowlist_dns_with(&policy, &resolver).await;
+        let status = |host: &str| report.iter().find(|e| e.host == host).unwrap().status.clone();
+        assert_eq!(report.len(), 4); // Wildcard collapses onto trusted.com
+        assert_eq!(status("trusted.com"), DnsAuditStatus::Ok);
+        assert!(matches!(status("old.trusted.com"), DnsAuditStatus::Unresolvable(_)));
+        assert_eq!(status("docs.trusted.com"), DnsAuditStatus::TakeoverProne("trusted-docs.s3.amazonaws.com.".into()));
+        assert_eq!(status("intranet.trusted.com"), DnsAuditStatus::NonPublicAddress(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))));
+    }
+}
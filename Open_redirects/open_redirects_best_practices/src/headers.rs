@@ -0,0 +1,118 @@
+// Strips response headers that fingerprint the server stack (Server, X-Powered-By, ...)
+use std::sync::Arc;
+
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    Error,
+    body::{MessageBody, BoxBody},
+    http::header::{self, HeaderName, HeaderValue},
+    middleware::Next
+};
+
+/// Headers that name a framework, runtime or version and are removed by default
+pub const FINGERPRINT_HEADERS: [HeaderName; 6] = [
+    header::SERVER,
+    HeaderName::from_static("x-powered-by"),
+    HeaderName::from_static("x-aspnet-version"),
+    HeaderName::from_static("x-aspnetmvc-version"),
+    HeaderName::from_static("x-generator"),
+    HeaderName::from_static("x-runtime"),
+];
+
+/// Middleware state for removing fingerprinting headers from every response.
+/// With `server_value`, a fixed `Server` header replaces whatever the stack set.
+#[derive(Debug, Clone)]
+pub struct ScrubHeaders {
+    remove: Arc<Vec<HeaderName>>,
+    server: Option<HeaderValue>,
+}
+
+impl Default for ScrubHeaders {
+    fn default() -> Self {
+        Self { remove: Arc::new(FINGERPRINT_HEADERS.to_vec()), server: None }
+    }
+}
+
+impl ScrubHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends `Server: <value>` instead of dropping the header, for clients that expect one
+    pub fn server_value(mut self, value: &'static str) -> Self {
+        self.server = Some(HeaderValue::from_static(value));
+        self
+    }
+
+    /// Removes `name` too, e.g. a header a proxy or framework adds in your deployment
+    pub fn also_remove(mut self, name: HeaderName) -> Self {
+        Arc::make_mut(&mut self.remove).push(name);
+        self
+    }
+
+    /// Middleware body: runs the handler, then scrubs its response headers.
+    /// Wrap with `from_fn(move |req, next| scrub.clone().guard(req, next))`.
+    pub async fn guard(
+        self,
+        req: ServiceRequest,
+        next: Next<impl MessageBody + 'static>,
+    ) -> Result<ServiceResponse<BoxBody>, Error> {
+        let mut res = next.call(req).await?;
+        let headers = res.headers_mut();
+        for name in self.remove.iter() {
+            headers.remove(name);
+        }
+        if let Some(server) = self.server {
+            headers.insert(header::SERVER, server);
+        }
+        Ok(res.map_into_boxed_body())
+    }
+}
+
+/// `ScrubHeaders` with the defaults, for `from_fn(scrub_headers)`
+pub async fn scrub_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    ScrubHeaders::default().guard(req, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse, middleware::from_fn};
+    use actix_web::test::{call_service, init_service, TestRequest};
+
+    async fn chatty() -> HttpResponse {
+        HttpResponse::Ok()
+            .insert_header((header::SERVER, "actix-web/4.9.0"))
+            .insert_header(("X-Powered-By", "Rust/1.85"))
+            .insert_header(("X-Custom", "kept"))
+            .finish()
+    }
+
+    #[actix_web::test]
+    async fn test_fingerprint_headers_removed() {
+        // Server and X-Powered-By are gone; unrelated headers survive.
+        let app = init_service(App::new().wrap(from_fn(scrub_headers)).route("/", web::get().to(chatty))).await;
+        let resp = call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert!(resp.headers().get(header::SERVER).is_none());
+        assert!(resp.headers().get("x-powered-by").is_none());
+        assert_eq!(resp.headers().get("x-custom").unwrap(), "kept");
+    }
+
+    #[actix_web::test]
+    async fn test_server_override_replaces_version() {
+        // The configured value replaces the versioned Server header.
+        let scrub = ScrubHeaders::new().server_value("web").also_remove(HeaderName::from_static("x-custom"));
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| scrub.clone().guard(req, next)))
+                .route("/", web::get().to(chatty)),
+        )
+        .await;
+        let resp = call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(resp.headers().get(header::SERVER).unwrap(), "web");
+        assert!(resp.headers().get("x-custom").is_none());
+    }
+}
@@ -0,0 +1,155 @@
+// HTTP -> HTTPS upgrade that can't itself be turned into an open redirect
+use std::sync::Arc;
+
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+    body::{MessageBody, BoxBody},
+    http::header,
+    middleware::Next
+};
+use url::Url;
+
+use crate::proxy::{request_is_secure, TrustedProxies};
+
+/// Middleware state for upgrading plain-HTTP requests to HTTPS.
+/// Only hosts on `allowed_hosts` are ever used as the redirect target.
+#[derive(Debug, Clone)]
+pub struct HttpsRedirect {
+    allowed_hosts: Arc<Vec<String>>,
+    proxies: TrustedProxies,
+}
+
+impl HttpsRedirect {
+    pub fn new<I, S>(allowed_hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let allowed_hosts = allowed_hosts.into_iter().map(|h| h.as_ref().to_ascii_lowercase()).collect();
+        Self { allowed_hosts: Arc::new(allowed_hosts), proxies: TrustedProxies::default() }
+    }
+
+    /// Treats requests that `proxies` report as HTTPS as already secure
+    pub fn behind_proxies(mut self, proxies: TrustedProxies) -> Self {
+        self.proxies = proxies;
+        self
+    }
+
+    /// Middleware body: passes secure requests through, 301s plain HTTP to HTTPS,
+    /// answers 400 when the Host header isn't one of ours.
+    /// Wrap with `from_fn(move |req, next| https.clone().guard(req, next))`.
+    pub async fn guard(
+        self,
+        req: ServiceRequest,
+        next: Next<impl MessageBody + 'static>,
+    ) -> Result<ServiceResponse<BoxBody>, Error> {
+        if request_is_secure(req.request(), &self.proxies) {
+            return next.call(req).await.map(|res| res.map_into_boxed_body());
+        }
+
+        let response = match self.upgrade_target(&req) {
+            Some(target) => HttpResponse::MovedPermanently()
+                .append_header((header::LOCATION, target.as_str()))
+                .finish(),
+            None => {
+                tracing::warn!(target: "redirect", "Refused HTTPS upgrade for untrusted Host header");
+                HttpResponse::BadRequest().body("Unknown host")
+            }
+        };
+        Ok(req.into_response(response.map_into_boxed_body()))
+    }
+
+    // https://<allowed host><original path and query>, or None for a foreign Host
+    fn upgrade_target(&self, req: &ServiceRequest) -> Option<Url> {
+        let host = req.headers().get(header::HOST)?.to_str().ok()?;
+        let host = host.rsplit_once(':').map_or(host, |(name, _port)| name).to_ascii_lowercase();
+        if !self.allowed_hosts.contains(&host) {
+            return None;
+        }
+        let mut target = Url::parse(&format!("https://{}", host)).ok()?;
+        target.set_path(req.path());
+        target.set_query(Some(req.query_string()).filter(|q| !q.is_empty()));
+        Some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, middleware::from_fn, http::StatusCode};
+
+    const PROXY: &str = "10.0.0.2:40000";
+
+    fn upgrade() -> HttpsRedirect {
+        HttpsRedirect::new(["trusted.com"]).behind_proxies(TrustedProxies::new([PROXY.parse::<std::net::SocketAddr>().unwrap().ip()]))
+    }
+
+    #[actix_web::test]
+    async fn test_http_request_is_upgraded() {
+        // A plain HTTP request is sent to the same host, path and query over HTTPS.
+        let https = upgrade();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| https.clone().guard(req, next)))
+                .route("/login", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/login?next=dash")
+            .insert_header((header::HOST, "trusted.com:8080"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), "https://trusted.com/login?next=dash");
+    }
+
+    #[actix_web::test]
+    async fn test_https_request_passes_through() {
+        // The trusted proxy's header marks the request as already TLS-terminated.
+        let https = upgrade();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| https.clone().guard(req, next)))
+                .route("/login", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/login")
+            .insert_header((header::HOST, "trusted.com"))
+            .insert_header(("x-forwarded-proto", "https"))
+            .peer_addr(PROXY.parse().unwrap())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // The same header straight from a client is not believed
+        let req = test::TestRequest::get()
+            .uri("/login")
+            .insert_header((header::HOST, "trusted.com"))
+            .insert_header(("x-forwarded-proto", "https"))
+            .peer_addr("203.0.113.9:40000".parse().unwrap())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    }
+
+    #[actix_web::test]
+    async fn test_foreign_host_is_not_redirected() {
+        // A forged Host header can't make the upgrade point somewhere else.
+        let https = upgrade();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| https.clone().guard(req, next)))
+                .route("/login", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/login")
+            .insert_header((header::HOST, "evil.com"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(resp.headers().get(header::LOCATION).is_none());
+    }
+}
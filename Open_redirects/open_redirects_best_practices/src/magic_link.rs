@@ -0,0 +1,219 @@
+// Signed, time-limited redirect links for email confirmation and magic sign-in
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use url::Url;
+
+use crate::clock::{Clock, SystemClock};
+use crate::{validate_redirect_url, RedirectError};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Domain separation: a MAC for anything else can never verify as a magic link
+const TOKEN_VERSION: &str = "ml1";
+
+/// Reasons a magic link is refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MagicLinkError {
+    Malformed,                // Not a token we could have minted
+    InvalidSignature,         // Tampered with, or minted under another key
+    Expired,                  // Past its expiry time
+    AlreadyUsed,              // Single-use token presented a second time
+    Redirect(RedirectError),  // Target no longer passes the redirect allow-list
+}
+
+impl fmt::Display for MagicLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "Malformed magic link"),
+            Self::InvalidSignature => write!(f, "Invalid magic link signature"),
+            Self::Expired => write!(f, "Magic link has expired"),
+            Self::AlreadyUsed => write!(f, "Magic link has already been used"),
+            Self::Redirect(e) => write!(f, "Magic link target rejected: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MagicLinkError {}
+
+/// Remembers consumed tokens so each link works once.
+/// Back it with Redis `SET NX PX` or a unique-keyed table to share it across nodes.
+pub trait SeenTokenStore {
+    /// Records `token_id` until `expires_at`; returns false if it was already recorded
+    fn mark_seen(&self, token_id: &str, expires_at: u64) -> bool;
+}
+
+/// Process-local `SeenTokenStore`; entries are pruned once their link has expired
+#[derive(Debug, Default)]
+pub struct InMemorySeenTokens<C: Clock = SystemClock> {
+    seen: Mutex<HashMap<String, u64>>,
+    clock: C,
+}
+
+impl InMemorySeenTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C: Clock> InMemorySeenTokens<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Self { seen: Mutex::new(HashMap::new()), clock }
+    }
+}
+
+impl<C: Clock> SeenTokenStore for InMemorySeenTokens<C> {
+    fn mark_seen(&self, token_id: &str, expires_at: u64) -> bool {
+        let now = self.clock.unix_secs();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, exp| *exp >= now); // An expired link is refused anyway, no need to remember it
+        seen.insert(token_id.to_string(), expires_at).is_none()
+    }
+}
+
+/// Mints a token for `url` that is valid for `ttl`.
+/// Format: base64url(url) "." expiry-unix-seconds "." base64url(HMAC-SHA256)
+pub fn mint_magic_link(url: &str, key: &[u8], ttl: Duration) -> String {
+    mint_magic_link_with_clock(url, key, ttl, &SystemClock)
+}
+
+/// `mint_magic_link` with an explicit time source
+pub fn mint_magic_link_with_clock(url: &str, key: &[u8], ttl: Duration, clock: &impl Clock) -> String {
+    let expires_at = clock.unix_secs() + ttl.as_secs();
+    let encoded_url = URL_SAFE_NO_PAD.encode(url);
+    let mac = sign(key, &encoded_url, expires_at).finalize().into_bytes();
+    format!("{}.{}.{}", encoded_url, expires_at, URL_SAFE_NO_PAD.encode(mac))
+}
+
+/// Checks signature, then expiry, then re-validates the target against the allow-list.
+/// Stateless: pair with `consume_magic_link_once` when links must be single-use.
+pub fn consume_magic_link(token: &str, key: &[u8]) -> Result<Url, MagicLinkError> {
+    consume_magic_link_with_clock(token, key, &SystemClock)
+}
+
+/// `consume_magic_link` with an explicit time source
+pub fn consume_magic_link_with_clock(token: &str, key: &[u8], clock: &impl Clock) -> Result<Url, MagicLinkError> {
+    let (encoded_url, expires_at, mac) = parse_token(token)?;
+
+    // Constant-time comparison via verify_slice
+    sign(key, encoded_url, expires_at)
+        .verify_slice(&mac)
+        .map_err(|_| MagicLinkError::InvalidSignature)?;
+
+    if clock.unix_secs() > expires_at {
+        return Err(MagicLinkError::Expired);
+    }
+
+    let url = URL_SAFE_NO_PAD
+        .decode(encoded_url)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or(MagicLinkError::Malformed)?;
+    validate_redirect_url(&url).map(|decision| decision.url).map_err(MagicLinkError::Redirect)
+}
+
+/// Like `consume_magic_link`, but refuses a token already recorded in `seen`
+pub fn consume_magic_link_once(token: &str, key: &[u8], seen: &impl SeenTokenStore) -> Result<Url, MagicLinkError> {
+    consume_magic_link_once_with_clock(token, key, seen, &SystemClock)
+}
+
+/// `consume_magic_link_once` with an explicit time source
+pub fn consume_magic_link_once_with_clock(
+    token: &str,
+    key: &[u8],
+    seen: &impl SeenTokenStore,
+    clock: &impl Clock,
+) -> Result<Url, MagicLinkError> {
+    let url = consume_magic_link_with_clock(token, key, clock)?;
+    let (_, expires_at, mac) = parse_token(token)?;
+    // Keyed on the MAC: unique per (url, expiry) and already authenticated above
+    if !seen.mark_seen(&URL_SAFE_NO_PAD.encode(mac), expires_at) {
+        return Err(MagicLinkError::AlreadyUsed);
+    }
+    Ok(url)
+}
+
+fn parse_token(token: &str) -> Result<(&str, u64, Vec<u8>), MagicLinkError> {
+    let mut parts = token.split('.');
+    let (Some(encoded_url), Some(expires_at), Some(mac), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(MagicLinkError::Malformed);
+    };
+    let expires_at = expires_at.parse().map_err(|_| MagicLinkError::Malformed)?;
+    let mac = URL_SAFE_NO_PAD.decode(mac).map_err(|_| MagicLinkError::Malformed)?;
+    Ok((encoded_url, expires_at, mac))
+}
+
+fn sign(key: &[u8], encoded_url: &str, expires_at: u64) -> HmacSha256 {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(TOKEN_VERSION.as_bytes());
+    mac.update(b"|");
+    mac.update(encoded_url.as_bytes());
+    mac.update(b"|");
+    mac.update(expires_at.to_string().as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    const KEY: &[u8] = b"magic-link-test-key-0123456789ab";
+
+    #[test]
+    fn test_valid_link_round_trips() {
+        // A fresh link resolves to its allow-listed target.
+        let token = mint_magic_link("https://trusted.com/confirm?u=42", KEY, Duration::from_secs(600));
+        assert_eq!(consume_magic_link(&token, KEY).unwrap().as_str(), "https://trusted.com/confirm?u=42");
+    }
+
+    #[test]
+    fn test_expired_link_rejected() {
+        // Advancing a mock clock past the TTL expires the link without sleeping.
+        let clock = MockClock::default();
+        let token = mint_magic_link_with_clock("https://trusted.com/", KEY, Duration::from_secs(600), &clock);
+        clock.advance(Duration::from_secs(600));
+        assert!(consume_magic_link_with_clock(&token, KEY, &clock).is_ok());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(consume_magic_link_with_clock(&token, KEY, &clock), Err(MagicLinkError::Expired));
+    }
+
+    #[test]
+    fn test_tampered_link_rejected() {
+        // Swapping the target, extending the expiry or using another key breaks the signature.
+        let token = mint_magic_link("https://trusted.com/", KEY, Duration::from_secs(600));
+        let parts: Vec<&str> = token.split('.').collect();
+
+        let evil = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode("https://evil.com/"), parts[1], parts[2]);
+        assert_eq!(consume_magic_link(&evil, KEY), Err(MagicLinkError::InvalidSignature));
+        let extended = format!("{}.{}.{}", parts[0], u64::MAX, parts[2]);
+        assert_eq!(consume_magic_link(&extended, KEY), Err(MagicLinkError::InvalidSignature));
+        assert_eq!(consume_magic_link(&token, b"other-key"), Err(MagicLinkError::InvalidSignature));
+        assert_eq!(consume_magic_link("not-a-token", KEY), Err(MagicLinkError::Malformed));
+    }
+
+    #[test]
+    fn test_reused_link_rejected() {
+        // The second presentation of a single-use link fails.
+        let seen = InMemorySeenTokens::new();
+        let token = mint_magic_link("https://docs.trusted.com/welcome", KEY, Duration::from_secs(600));
+        assert!(consume_magic_link_once(&token, KEY, &seen).is_ok());
+        assert_eq!(consume_magic_link_once(&token, KEY, &seen), Err(MagicLinkError::AlreadyUsed));
+    }
+
+    #[test]
+    fn test_signed_offsite_target_still_rejected() {
+        // Even a correctly signed link can't point outside the allow-list.
+        let token = mint_magic_link("https://evil.com/", KEY, Duration::from_secs(600));
+        assert_eq!(
+            consume_magic_link(&token, KEY),
+            Err(MagicLinkError::Redirect(RedirectError::UntrustedDomain))
+        );
+    }
+}
@@ -0,0 +1,560 @@
+// Redirect policy: the allow-list and the checks applied to every redirect target
+use std::collections::HashSet;
+use std::fmt;
+
+use url::Url;
+
+use crate::{RedirectError, ALLOWED_DOMAINS};
+
+// Longest input echoed back by `classify` for malformed URLs
+const MAX_NORMALIZED_LEN: usize = 256;
+
+/// Default cap on allow-list size for `RedirectPolicy::with_max_entries`
+pub const DEFAULT_MAX_ALLOWLIST_ENTRIES: usize = 10_000;
+
+// Loopback hosts added by `RedirectPolicy::dev`
+const DEV_HOSTS: [&str; 2] = ["localhost", "127.0.0.1"];
+
+/// Runtime redirect policy built from an allow-list of trusted hosts.
+/// Entries are exact hosts (`trusted.com`) or wildcards (`*.trusted.com`, subdomains only).
+/// Both live in hash sets, so lookup cost depends on the host's length, not the list size.
+#[derive(Debug, Clone)]
+pub struct RedirectPolicy {
+    exact_hosts: HashSet<String>,
+    wildcard_suffixes: HashSet<String>, // "*.trusted.com" stored as "trusted.com"
+    denied: Vec<DenyRule>,              // Checked after the allow-list; deny wins
+    allowed_schemes: HashSet<String>,   // {"https"} unless widened, e.g. by `dev()`
+}
+
+/// Deny-list entry: a host plus a path prefix matched on segment boundaries
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DenyRule {
+    host: String,
+    path_prefix: String, // Already in `deny_path_form`
+}
+
+impl DenyRule {
+    // "trusted.com/admin-panel" denies /admin-panel and /admin-panel/..., not /admin-panels
+    fn matches(&self, host: &str, path: &str) -> bool {
+        if host != self.host {
+            return false;
+        }
+        let path = deny_path_form(path);
+        let prefix = self.path_prefix.trim_end_matches('/');
+        match path.strip_prefix(prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+// The spelling a lenient server would resolve `path` to, so aliases of a denied path match too:
+// unreserved characters percent-decoded (`%61dmin` is `admin`), other escapes kept with uppercase
+// hex, repeated slashes collapsed, dot segments resolved again, and ASCII lowercased. Lowercasing
+// over-matches on case-sensitive servers, which is the safe direction for a deny-list.
+fn deny_path_form(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = String::with_capacity(path.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') => {
+                decoded.push(char::from(b));
+                i += 3;
+            }
+            Some(b) => {
+                decoded.push_str(&format!("%{:02X}", b));
+                i += 3;
+            }
+            None => {
+                let c = path[i..].chars().next().unwrap_or_default();
+                decoded.push(c);
+                i += c.len_utf8().max(1);
+            }
+        }
+    }
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in decoded.split('/').filter(|s| !s.is_empty()) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let trailing = if decoded.ends_with('/') && !segments.is_empty() { "/" } else { "" };
+    format!("/{}{}", segments.join("/"), trailing).to_ascii_lowercase()
+}
+
+/// Errors building a `RedirectPolicy`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    TooManyEntries { len: usize, max: usize },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooManyEntries { len, max } => write!(f, "Allow-list has {} entries, maximum is {}", len, max),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+/// Outcome of evaluating a redirect target against the policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Block,
+}
+
+/// Non-throwing view of a redirect target, safe to write to logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectClassification {
+    pub normalized: String,            // Canonical URL, or an escaped/truncated copy if unparseable
+    pub decision: Decision,
+    pub reason: Option<RedirectError>, // Set whenever decision is Block
+}
+
+/// A validated redirect target, parsed once and shared by everything downstream
+/// (middleware, logs, the `Location` header), so nobody has to reparse it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectDecision {
+    pub url: Url,
+    pub canonical: String, // Normalized serialization of `url`; use it as the `Location` value
+    pub host: String,      // Lowercased host that passed the allow-list
+}
+
+impl From<Url> for RedirectDecision {
+    fn from(url: Url) -> Self {
+        let canonical = url.to_string();
+        let host = url.host_str().unwrap_or_default().to_string();
+        Self { url, canonical, host }
+    }
+}
+
+impl RedirectPolicy {
+    pub fn new<I, S>(allowed_domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut exact_hosts = HashSet::new();
+        let mut wildcard_suffixes = HashSet::new();
+        for entry in allowed_domains {
+            let entry = entry.into().to_ascii_lowercase();
+            match entry.strip_prefix("*.") {
+                Some(suffix) => wildcard_suffixes.insert(suffix.to_string()),
+                None => exact_hosts.insert(entry),
+            };
+        }
+        let allowed_schemes = HashSet::from(["https".to_string()]);
+        Self { exact_hosts, wildcard_suffixes, denied: Vec::new(), allowed_schemes }
+    }
+
+    /// LOCAL DEVELOPMENT ONLY: the default allow-list plus `http` and the loopback hosts
+    /// `localhost` and `127.0.0.1`, so redirects to a dev server work. Never use in production:
+    /// plain http targets can be rewritten in transit. External http hosts are still refused.
+    pub fn dev() -> Self {
+        tracing::warn!(
+            target: "redirect",
+            "Using the development redirect policy: http and localhost targets are allowed"
+        );
+        let mut policy = Self::default().allow_schemes(["http"]);
+        policy.exact_hosts.extend(DEV_HOSTS.map(String::from));
+        policy
+    }
+
+    /// Adds URL schemes (lowercase, without `:`) that targets may use besides `https`
+    pub fn allow_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_schemes.extend(schemes.into_iter().map(|s| s.into().to_ascii_lowercase()));
+        self
+    }
+
+    /// Schemes accepted by `validate`
+    pub fn allowed_schemes(&self) -> &HashSet<String> {
+        &self.allowed_schemes
+    }
+
+    /// Adds deny entries of the form `host/path-prefix` (`trusted.com/admin-panel`).
+    /// A bare host denies the whole host. Paths are compared case-insensitively, after
+    /// decoding percent-escaped unreserved characters, collapsing repeated slashes and
+    /// resolving dot segments, so `/%61dmin-panel`, `//admin-panel` and `/Admin-Panel`
+    /// are all denied along with `/admin-panel`.
+    pub fn deny<I, S>(mut self, entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for entry in entries {
+            let entry = entry.into();
+            let (host, path) = entry.split_once('/').map_or((entry.as_str(), ""), |(h, p)| (h, p));
+            let path_prefix = deny_path_form(&format!("/{}", path));
+            self.denied.push(DenyRule { host: host.to_ascii_lowercase(), path_prefix });
+        }
+        self
+    }
+
+    /// Like `new`, but refuses allow-lists larger than `max_entries`
+    pub fn with_max_entries<I, S>(allowed_domains: I, max_entries: usize) -> Result<Self, PolicyError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let entries: Vec<S> = allowed_domains.into_iter().collect();
+        if entries.len() > max_entries {
+            return Err(PolicyError::TooManyEntries { len: entries.len(), max: max_entries });
+        }
+        Ok(Self::new(entries))
+    }
+
+    /// Number of allow-list entries, exact and wildcard
+    pub fn len(&self) -> usize {
+        self.exact_hosts.len() + self.wildcard_suffixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every host the allow-list names, sorted: exact entries as-is, wildcards
+    /// by their base domain (`*.trusted.com` -> `trusted.com`), without duplicates
+    pub fn hosts(&self) -> Vec<&str> {
+        let mut hosts: Vec<&str> = self.exact_hosts.iter().chain(&self.wildcard_suffixes).map(String::as_str).collect();
+        hosts.sort_unstable();
+        hosts.dedup();
+        hosts
+    }
+
+    /// Validates user-provided redirect URLs against security best practices
+    /// Returns parsed Url if valid, or RedirectError if any checks fail
+    pub fn validate(&self, input: &str) -> Result<Url, RedirectError> {
+        // Reject //host up front: resolved against our origin it leaves the site
+        if is_protocol_relative(input) {
+            return Err(RedirectError::ProtocolRelative);
+        }
+
+        // Parse input string into Url object
+        let parsed_url = Url::parse(input)
+            .map_err(|_| RedirectError::InvalidUrl)?;
+
+        // Normalize path segments to prevent path traversal attacks
+        // This ensures URLs with encoded characters (e.g., %2F) are properly handled
+        parsed_url
+            .path_segments()
+            .map(|segments| segments.collect::<Vec<_>>())
+            .ok_or(RedirectError::InvalidPath)?;
+
+        // The path must decode to valid UTF-8: %C0%AF is an overlong '/' that a lenient
+        // decoder downstream could turn into a traversal
+        percent_decode_utf8(parsed_url.path())?;
+
+        // Security checks:
+        // 1. Enforce HTTPS (or the configured schemes): no downgrades, no javascript:/data: targets
+        if !self.allowed_schemes.contains(parsed_url.scheme()) {
+            return Err(RedirectError::DisallowedScheme);
+        }
+
+        // 2. No userinfo: https://trusted.com@evil.com tricks readers, user:pass@ leaks secrets
+        if !parsed_url.username().is_empty() || parsed_url.password().is_some() {
+            return Err(RedirectError::EmbeddedCredentials);
+        }
+
+        // 3. Verify host is in our allow-list
+        let host = parsed_url.host_str().unwrap_or("");
+        if !self.is_allowed_host(host) {
+            return Err(RedirectError::UntrustedDomain);
+        }
+
+        // 4. Deny-list: carves exceptions out of allowed hosts, so it always wins
+        if self.denied.iter().any(|rule| rule.matches(host, parsed_url.path())) {
+            return Err(RedirectError::Denied);
+        }
+
+        Ok(parsed_url)
+    }
+
+    /// Like `validate`, but also returns the canonical string and host computed from the one parse
+    pub fn decide(&self, input: &str) -> Result<RedirectDecision, RedirectError> {
+        self.validate(input).map(RedirectDecision::from)
+    }
+
+    /// Classifies a redirect target without rejecting it, so callers can record
+    /// where users tried to go. Never panics, whatever the input.
+    pub fn classify(&self, input: &str) -> RedirectClassification {
+        match self.decide(input) {
+            Ok(decision) => {
+                RedirectClassification { normalized: decision.canonical, decision: Decision::Allow, reason: None }
+            }
+            Err(e) => {
+                let normalized = match Url::parse(input) {
+                    Ok(url) => url.to_string(),
+                    Err(_) => escape_for_log(input),
+                };
+                RedirectClassification { normalized, decision: Decision::Block, reason: Some(e) }
+            }
+        }
+    }
+
+    /// `classify` for a raw (still percent-encoded) query parameter value, decoded
+    /// with `decode_redirect_param` first; undecodable input is blocked as `InvalidEncoding`
+    pub fn classify_param(&self, raw: &str) -> RedirectClassification {
+        match decode_redirect_param(raw) {
+            Ok(decoded) => self.classify(&decoded),
+            Err(e) => RedirectClassification {
+                normalized: escape_for_log(raw),
+                decision: Decision::Block,
+                reason: Some(e),
+            },
+        }
+    }
+
+    fn is_allowed_host(&self, host: &str) -> bool {
+        self.lookup_host(host).0
+    }
+
+    // Also returns how many set lookups it made: one per label of `host`, whatever the list sizes
+    fn lookup_host(&self, host: &str) -> (bool, usize) {
+        let mut probes = 1;
+        if self.exact_hosts.contains(host) {
+            return (true, probes);
+        }
+        // Walk parent domains: a.b.trusted.com -> b.trusted.com -> trusted.com -> com
+        let mut rest = host;
+        while let Some((_, parent)) = rest.split_once('.') {
+            probes += 1;
+            if self.wildcard_suffixes.contains(parent) {
+                return (true, probes);
+            }
+            rest = parent;
+        }
+        (false, probes)
+    }
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::new(ALLOWED_DOMAINS)
+    }
+}
+
+// Browsers skip leading whitespace/control chars and treat '\' like '/',
+// so "//evil.com", "\/\/evil.com" and "/\evil.com" all mean https://evil.com
+fn is_protocol_relative(input: &str) -> bool {
+    let mut chars = input.trim_start_matches(|c: char| c.is_whitespace() || c.is_control()).chars();
+    matches!((chars.next(), chars.next()), (Some('/' | '\\'), Some('/' | '\\')))
+}
+
+/// Percent-decodes a raw `redirect=` query value. Fails with `InvalidEncoding` on a
+/// malformed escape or bytes that aren't valid UTF-8 (overlong forms, lone continuation
+/// bytes, surrogates), instead of decoding lossily. `+` is left as is
+pub fn decode_redirect_param(raw: &str) -> Result<String, RedirectError> {
+    percent_decode_utf8(raw)
+}
+
+fn percent_decode_utf8(input: &str) -> Result<String, RedirectError> {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut rest = input.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok());
+            let byte = hex.and_then(|h| u8::from_str_radix(h, 16).ok()).ok_or(RedirectError::InvalidEncoding)?;
+            bytes.push(byte);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    // Rust's UTF-8 validation rejects overlong encodings, which lossy decoders may accept
+    String::from_utf8(bytes).map_err(|_| RedirectError::InvalidEncoding)
+}
+
+// Escapes control characters (log injection) and caps the length of raw input
+fn escape_for_log(input: &str) -> String {
+    let mut escaped: String = input.chars().take(MAX_NORMALIZED_LEN).flat_map(char::escape_debug).collect();
+    if input.chars().count() > MAX_NORMALIZED_LEN {
+        escaped.push_str("...");
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_matches_subdomains_only() {
+        // "*.trusted.com" covers any depth of subdomain but not the apex or look-alikes.
+        let policy = RedirectPolicy::new(["*.trusted.com"]);
+        assert!(policy.validate("https://a.trusted.com/").is_ok());
+        assert!(policy.validate("https://a.b.trusted.com/").is_ok());
+        assert!(policy.validate("https://trusted.com/").is_err());
+        assert!(policy.validate("https://eviltrusted.com/").is_err());
+        assert!(policy.validate("https://trusted.com.evil.com/").is_err());
+    }
+
+    #[test]
+    fn test_decision_carries_canonical_form() {
+        // One parse yields the URL, its normalized string and host; classify reuses the same string.
+        let policy = RedirectPolicy::default();
+        let decision = policy.decide("HTTPS://Docs.Trusted.COM:443/a/./b/../c?q=1#top").unwrap();
+        assert_eq!(decision.canonical, "https://docs.trusted.com/a/c?q=1#top");
+        assert_eq!(decision.host, "docs.trusted.com");
+        assert_eq!(decision.url.as_str(), decision.canonical);
+        assert_eq!(policy.classify("HTTPS://Docs.Trusted.COM:443/a/./b/../c?q=1#top").normalized, decision.canonical);
+        assert_eq!(policy.decide("https://evil.com/"), Err(RedirectError::UntrustedDomain));
+    }
+
+    #[test]
+    fn test_invalid_encoding_rejected_before_host_check() {
+        // Overlong '/' and stray bytes fail as InvalidEncoding, even on an allowed host; valid escapes pass.
+        assert_eq!(decode_redirect_param("https://trusted.com/%C0%AFetc"), Err(RedirectError::InvalidEncoding));
+        assert_eq!(decode_redirect_param("https://trusted.com/%FF%FE"), Err(RedirectError::InvalidEncoding));
+        assert_eq!(decode_redirect_param("https://trusted.com/%2"), Err(RedirectError::InvalidEncoding));
+        assert_eq!(decode_redirect_param("https://evil.com/%E0%80%AF"), Err(RedirectError::InvalidEncoding));
+
+        let policy = RedirectPolicy::default();
+        assert_eq!(policy.validate("https://trusted.com/%C0%AE%C0%AE/admin"), Err(RedirectError::InvalidEncoding));
+        let classification = policy.classify_param("https%3A%2F%2Ftrusted.com%2F%C0%AF");
+        assert_eq!(classification.reason, Some(RedirectError::InvalidEncoding));
+
+        let decoded = decode_redirect_param("https%3A%2F%2Ftrusted.com%2Fdocs%2Fcaf%C3%A9%20menu").unwrap();
+        assert_eq!(decoded, "https://trusted.com/docs/café menu");
+        assert_eq!(policy.decide(&decoded).unwrap().canonical, "https://trusted.com/docs/caf%C3%A9%20menu");
+        assert!(policy.validate("https://trusted.com/caf%C3%A9").is_ok());
+    }
+
+    #[test]
+    fn test_max_entries_enforced() {
+        // Oversized allow-lists are refused up front.
+        let hosts: Vec<String> = (0..11).map(|i| format!("h{i}.example.com")).collect();
+        assert_eq!(
+            RedirectPolicy::with_max_entries(hosts.clone(), 10).unwrap_err(),
+            PolicyError::TooManyEntries { len: 11, max: 10 }
+        );
+        assert_eq!(RedirectPolicy::with_max_entries(hosts, 11).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn test_lookup_cost_independent_of_list_size() {
+        // 10 vs 10,000 entries: a lookup makes the same few hash probes, one per host label.
+        let probes = |entries: usize, host: &str| {
+            let mut hosts: Vec<String> = (0..entries).map(|i| format!("host{i}.example.com")).collect();
+            hosts.push("*.wild.example.com".into());
+            RedirectPolicy::new(hosts).lookup_host(host)
+        };
+        assert_eq!(probes(10, "missing.sub.example.org"), (false, 4));
+        assert_eq!(probes(10_000, "missing.sub.example.org"), (false, 4));
+        assert_eq!(probes(10_000, "host9999.example.com"), (true, 1));
+        assert_eq!(probes(10_000, "a.wild.example.com"), (true, 2));
+    }
+
+    #[test]
+    fn test_protocol_relative_rejected() {
+        // Scheme-relative forms, including backslash variants, get their own error.
+        let policy = RedirectPolicy::default();
+        for input in ["//evil.com", "//evil.com/path", "\\/\\/evil.com", "/\\evil.com", "\\\\evil.com", " \t//evil.com"] {
+            assert_eq!(policy.validate(input), Err(RedirectError::ProtocolRelative), "{input:?}");
+        }
+        assert_eq!(policy.validate("/dashboard"), Err(RedirectError::InvalidUrl));
+        assert!(policy.validate("https://trusted.com/").is_ok());
+    }
+
+    #[test]
+    fn test_default_policy_rejects_plain_http() {
+        // Production defaults refuse http, even to localhost.
+        let policy = RedirectPolicy::default();
+        assert_eq!(policy.validate("http://localhost:3000/callback"), Err(RedirectError::DisallowedScheme));
+        assert_eq!(policy.validate("http://trusted.com/"), Err(RedirectError::DisallowedScheme));
+    }
+
+    #[test]
+    fn test_dev_policy_allows_local_http_only() {
+        // The dev preset accepts http to loopback hosts but not to arbitrary external ones.
+        let policy = RedirectPolicy::dev();
+        assert!(policy.validate("http://localhost:3000/callback").is_ok());
+        assert!(policy.validate("http://127.0.0.1:8080/").is_ok());
+        assert!(policy.validate("https://trusted.com/").is_ok());
+        assert_eq!(policy.validate("http://evil.com/"), Err(RedirectError::UntrustedDomain));
+        assert_eq!(policy.validate("ftp://localhost/"), Err(RedirectError::DisallowedScheme));
+    }
+
+    #[test]
+    fn test_deny_list_overrides_allow_list() {
+        // An allowed host is rejected on a denied path prefix, including sub-paths.
+        let policy = RedirectPolicy::default().deny(["trusted.com/admin-panel"]);
+        assert_eq!(policy.validate("https://trusted.com/admin-panel"), Err(RedirectError::Denied));
+        assert_eq!(policy.validate("https://TRUSTED.com/admin-panel/users"), Err(RedirectError::Denied));
+        assert_eq!(policy.validate("https://trusted.com/x/../admin-panel"), Err(RedirectError::Denied));
+    }
+
+    #[test]
+    fn test_deny_list_catches_path_aliases() {
+        // Encoded letters, doubled slashes, other casing and encoded dot segments all reach the denied page.
+        let policy = RedirectPolicy::default().deny(["trusted.com/admin-panel"]);
+        for alias in [
+            "https://trusted.com/%61dmin-panel",
+            "https://trusted.com/%41DMIN%2dpanel/users",
+            "https://trusted.com//admin-panel",
+            "https://trusted.com///admin-panel//users",
+            "https://trusted.com/Admin-Panel",
+            "https://trusted.com/x/%2E%2E//admin-panel",
+        ] {
+            assert_eq!(policy.validate(alias), Err(RedirectError::Denied), "{}", alias);
+        }
+        assert!(policy.validate("https://trusted.com/admin%2Dpanels").is_ok());
+        assert!(policy.validate("https://trusted.com/admin%2Fpanel").is_ok()); // Encoded slash is not a separator
+    }
+
+    #[test]
+    fn test_deny_list_leaves_other_paths_alone() {
+        // Paths and hosts not covered by a deny entry are still allowed.
+        let policy = RedirectPolicy::default().deny(["trusted.com/admin-panel", "docs.trusted.com"]);
+        assert!(policy.validate("https://trusted.com/profile").is_ok());
+        assert!(policy.validate("https://trusted.com/admin-panels").is_ok());
+        assert!(policy.validate("https://api.trusted.com/admin-panel").is_ok());
+        assert_eq!(policy.validate("https://docs.trusted.com/any"), Err(RedirectError::Denied));
+    }
+
+    #[test]
+    fn test_classify_allowed_url() {
+        // An allow-listed URL is allowed and reported in canonical form.
+        let c = RedirectPolicy::default().classify("https://TRUSTED.com/dash");
+        assert_eq!(c.decision, Decision::Allow);
+        assert_eq!(c.normalized, "https://trusted.com/dash");
+        assert_eq!(c.reason, None);
+    }
+
+    #[test]
+    fn test_classify_blocked_offsite_url() {
+        // An off-site URL is blocked but still normalized for the audit log.
+        let c = RedirectPolicy::default().classify("https://evil.com/phish");
+        assert_eq!(c.decision, Decision::Block);
+        assert_eq!(c.normalized, "https://evil.com/phish");
+        assert_eq!(c.reason, Some(RedirectError::UntrustedDomain));
+    }
+
+    #[test]
+    fn test_classify_malformed_input() {
+        // Garbage input is escaped, not parsed, and never panics.
+        let c = RedirectPolicy::default().classify("not a url\r\n\u{0}");
+        assert_eq!(c.decision, Decision::Block);
+        assert_eq!(c.normalized, "not a url\\r\\n\\0");
+        assert_eq!(c.reason, Some(RedirectError::InvalidUrl));
+
+        let long = "x".repeat(10_000);
+        assert!(RedirectPolicy::default().classify(&long).normalized.len() <= MAX_NORMALIZED_LEN + 3);
+    }
+}
@@ -0,0 +1,170 @@
+// Trusted reverse proxies: telling TLS-terminated traffic from plain HTTP, and finding the real client
+use std::net::IpAddr;
+
+use actix_web::{
+    HttpRequest,
+    http::header::{HeaderMap, HeaderName}
+};
+
+/// Proxies whose forwarded-scheme and forwarded-for headers are believed.
+/// Requests from any other peer are judged on their own connection only.
+/// Handlers that key on the client (`token_redirect`'s rate limit) read it from
+/// `web::Data<TrustedProxies>` when the app registers one.
+#[derive(Debug, Clone)]
+pub struct TrustedProxies {
+    proxies: Vec<IpAddr>,
+    proto_header: HeaderName,
+    for_header: HeaderName,
+}
+
+impl TrustedProxies {
+    /// Trusts `X-Forwarded-Proto` from the given peer addresses
+    pub fn new<I: IntoIterator<Item = IpAddr>>(proxies: I) -> Self {
+        Self {
+            proxies: proxies.into_iter().collect(),
+            proto_header: HeaderName::from_static("x-forwarded-proto"),
+            for_header: HeaderName::from_static("x-forwarded-for"),
+        }
+    }
+
+    /// Uses a different header, e.g. `X-Forwarded-Scheme` or `CloudFront-Forwarded-Proto`
+    pub fn proto_header(mut self, header: HeaderName) -> Self {
+        self.proto_header = header;
+        self
+    }
+
+    /// Uses a different client-address header, e.g. `X-Real-IP` or `CF-Connecting-IP`
+    pub fn for_header(mut self, header: HeaderName) -> Self {
+        self.for_header = header;
+        self
+    }
+
+    pub fn is_trusted(&self, peer: IpAddr) -> bool {
+        self.proxies.contains(&peer)
+    }
+}
+
+impl Default for TrustedProxies {
+    /// No proxies: forwarded headers are never believed
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+/// Whether the client's connection is HTTPS: TLS on this listener, or a trusted
+/// proxy saying it terminated TLS. A forwarded header from any other peer is ignored.
+pub fn request_is_secure(req: &HttpRequest, config: &TrustedProxies) -> bool {
+    let peer = req.peer_addr().map(|addr| addr.ip());
+    scheme_is_secure(req.app_config().secure(), peer, req.headers(), config)
+}
+
+/// The client's address: the peer itself, or, when the peer is a trusted proxy, the
+/// rightmost forwarded-for entry that isn't another trusted proxy. Entries further left
+/// were written by the client and are never believed.
+pub fn client_ip(req: &HttpRequest, config: &TrustedProxies) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    Some(forwarded_client(peer, req.headers(), config))
+}
+
+fn forwarded_client(peer: IpAddr, headers: &HeaderMap, config: &TrustedProxies) -> IpAddr {
+    let mut client = peer;
+    if !config.is_trusted(peer) {
+        return client;
+    }
+    // Proxies append, so read the hops nearest to us first
+    let hops = headers.get_all(&config.for_header).filter_map(|v| v.to_str().ok()).collect::<Vec<_>>();
+    for hop in hops.iter().rev().flat_map(|v| v.rsplit(',')) {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) => {
+                client = ip;
+                if !config.is_trusted(ip) {
+                    break;
+                }
+            }
+            Err(_) => break, // Garbage: stop at the last hop we could read
+        }
+    }
+    client
+}
+
+fn scheme_is_secure(direct_tls: bool, peer: Option<IpAddr>, headers: &HeaderMap, config: &TrustedProxies) -> bool {
+    if direct_tls {
+        return true;
+    }
+    if !peer.is_some_and(|ip| config.is_trusted(ip)) {
+        return false;
+    }
+    // A proxy that appends rather than overwrites puts its own value last
+    headers
+        .get(&config.proto_header)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit(',').next())
+        .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    const PROXY: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    fn from_peer(peer: IpAddr, proto: &str) -> HttpRequest {
+        TestRequest::get()
+            .peer_addr(SocketAddr::new(peer, 40000))
+            .insert_header(("x-forwarded-proto", proto))
+            .to_http_request()
+    }
+
+    #[test]
+    fn test_trusted_proxy_header_means_https() {
+        // The load balancer's X-Forwarded-Proto is believed.
+        let config = TrustedProxies::new([PROXY]);
+        assert!(request_is_secure(&from_peer(PROXY, "https"), &config));
+        assert!(request_is_secure(&from_peer(PROXY, "http, https"), &config));
+        assert!(!request_is_secure(&from_peer(PROXY, "http"), &config));
+    }
+
+    #[test]
+    fn test_spoofed_header_from_untrusted_peer_ignored() {
+        // A client talking to us directly can't claim HTTPS with a header.
+        let config = TrustedProxies::new([PROXY]);
+        assert!(!request_is_secure(&from_peer(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9)), "https"), &config));
+        assert!(!request_is_secure(&from_peer(PROXY, "https"), &TrustedProxies::default()));
+    }
+
+    fn forwarded_for(peer: IpAddr, chain: &str) -> HttpRequest {
+        TestRequest::get()
+            .peer_addr(SocketAddr::new(peer, 40000))
+            .insert_header(("x-forwarded-for", chain))
+            .to_http_request()
+    }
+
+    #[test]
+    fn test_client_ip_from_trusted_proxy_chain() {
+        // Behind the proxy the client is the rightmost untrusted hop; client-written entries to its left are ignored.
+        let config = TrustedProxies::new([PROXY, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3))]);
+        let client = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7));
+        assert_eq!(client_ip(&forwarded_for(PROXY, "198.51.100.7"), &config), Some(client));
+        assert_eq!(client_ip(&forwarded_for(PROXY, "1.2.3.4, 198.51.100.7, 10.0.0.3"), &config), Some(client));
+        assert_eq!(client_ip(&forwarded_for(PROXY, "not-an-ip"), &config), Some(PROXY));
+    }
+
+    #[test]
+    fn test_client_ip_ignores_header_from_untrusted_peer() {
+        // A direct client can't pick its own rate-limit key.
+        let direct = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9));
+        let config = TrustedProxies::new([PROXY]);
+        assert_eq!(client_ip(&forwarded_for(direct, "198.51.100.7"), &config), Some(direct));
+        assert_eq!(client_ip(&forwarded_for(PROXY, "198.51.100.7"), &TrustedProxies::default()), Some(PROXY));
+    }
+
+    #[test]
+    fn test_direct_tls_is_secure() {
+        // TLS on our own listener counts regardless of peer or headers.
+        let headers = HeaderMap::new();
+        assert!(scheme_is_secure(true, None, &headers, &TrustedProxies::default()));
+        assert!(!scheme_is_secure(false, None, &headers, &TrustedProxies::default()));
+    }
+}
@@ -0,0 +1,132 @@
+// Per-IP limit on failed token guesses for `token_redirect`
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+
+/// Tracks failed attempts per client. `token_redirect` consults one when the app
+/// registers it as `web::Data<dyn AttemptLimiter>`; without one, guessing is unlimited.
+/// Clients are keyed by `client_ip`, so behind a proxy register `web::Data<TrustedProxies>` as well.
+pub trait AttemptLimiter: Send + Sync {
+    /// Seconds until `ip` may try again, or None if it isn't blocked
+    fn retry_after(&self, ip: IpAddr) -> Option<u64>;
+    /// Records one invalid attempt from `ip`
+    fn record_failure(&self, ip: IpAddr);
+}
+
+/// Counts invalid-token attempts per client IP in fixed windows.
+/// Once an IP reaches `max_failures` within `window`, it is refused until the window ends.
+#[derive(Debug)]
+pub struct FailureRateLimiter<C: Clock = SystemClock> {
+    failures: Mutex<HashMap<IpAddr, (u32, u64)>>, // ip -> (failures, window_start)
+    max_failures: u32,
+    window: Duration,
+    clock: C,
+}
+
+impl FailureRateLimiter {
+    pub fn new(max_failures: u32, window: Duration) -> Self {
+        Self::with_clock(max_failures, window, SystemClock)
+    }
+}
+
+impl<C: Clock> FailureRateLimiter<C> {
+    pub fn with_clock(max_failures: u32, window: Duration, clock: C) -> Self {
+        Self { failures: Mutex::new(HashMap::new()), max_failures, window, clock }
+    }
+}
+
+impl<C: Clock> AttemptLimiter for FailureRateLimiter<C> {
+    fn retry_after(&self, ip: IpAddr) -> Option<u64> {
+        let now = self.clock.unix_secs();
+        let failures = self.failures.lock().unwrap();
+        let (count, window_start) = *failures.get(&ip)?;
+        let window_end = window_start + self.window.as_secs();
+        (count >= self.max_failures && now < window_end).then(|| window_end - now)
+    }
+
+    fn record_failure(&self, ip: IpAddr) {
+        let now = self.clock.unix_secs();
+        let window = self.window.as_secs();
+        let mut failures = self.failures.lock().unwrap();
+        failures.retain(|_, (_, start)| *start + window > now); // Forget finished windows
+        let (count, _) = failures.entry(ip).or_insert((0, now));
+        *count = count.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::token_store::{InMemoryTokenStore, TokenStore};
+    use actix_web::{web, App, http::{header, StatusCode}};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_repeated_invalid_tokens_rate_limited() {
+        // After three misses an IP gets 429, even for a valid token; other IPs and later windows are unaffected.
+        let clock = MockClock::default();
+        let limiter: Arc<dyn AttemptLimiter> =
+            Arc::new(FailureRateLimiter::with_clock(3, Duration::from_secs(60), clock.clone()));
+        let store: Arc<dyn TokenStore> = Arc::new(InMemoryTokenStore::from([("profile", "https://trusted.com/me")]));
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::from(store))
+                .app_data(web::Data::from(limiter))
+                .service(crate::token_redirect),
+        )
+        .await;
+        let get = |token: &str, ip: &str| {
+            TestRequest::get()
+                .uri(&format!("/safe_redirect/{}", token))
+                .peer_addr(format!("{}:40000", ip).parse().unwrap())
+                .to_request()
+        };
+
+        for guess in ["aaaa", "aaab", "aaac"] {
+            assert_eq!(call_service(&app, get(guess, "198.51.100.7")).await.status(), StatusCode::NOT_FOUND);
+        }
+        let resp = call_service(&app, get("aaad", "198.51.100.7")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "60");
+        let resp = call_service(&app, get("profile", "198.51.100.7")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        assert_eq!(call_service(&app, get("profile", "203.0.113.5")).await.status(), StatusCode::FOUND);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(call_service(&app, get("profile", "198.51.100.7")).await.status(), StatusCode::FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_clients_behind_proxy_limited_separately() {
+        // Through a trusted load balancer one guesser is blocked by forwarded IP; other clients still get through.
+        let limiter: Arc<dyn AttemptLimiter> = Arc::new(FailureRateLimiter::new(1, Duration::from_secs(60)));
+        let store: Arc<dyn TokenStore> = Arc::new(InMemoryTokenStore::from([("profile", "https://trusted.com/me")]));
+        let balancer: std::net::SocketAddr = "10.0.0.2:40000".parse().unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::from(store))
+                .app_data(web::Data::from(limiter))
+                .app_data(web::Data::new(crate::TrustedProxies::new([balancer.ip()])))
+                .service(crate::token_redirect),
+        )
+        .await;
+        let get = |token: &str, client: &str| {
+            TestRequest::get()
+                .uri(&format!("/safe_redirect/{}", token))
+                .peer_addr(balancer)
+                .insert_header(("x-forwarded-for", client))
+                .to_request()
+        };
+
+        assert_eq!(call_service(&app, get("nope", "198.51.100.7")).await.status(), StatusCode::NOT_FOUND);
+        let resp = call_service(&app, get("profile", "198.51.100.7")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(call_service(&app, get("profile", "203.0.113.5")).await.status(), StatusCode::FOUND);
+    }
+}
@@ -0,0 +1,104 @@
+// Request correlation IDs: every log line for a request carries the same id
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    Error, HttpMessage,
+    body::{MessageBody, BoxBody},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Longest client-supplied id we accept before generating our own
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Correlation id of the current request, stored in request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Middleware that reads or generates an `X-Request-Id`, runs the rest of the
+/// chain inside a tracing span carrying it, and echoes it on the response
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    // Reuse the caller's id only if it is safe to log; otherwise mint a UUID
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_safe_request_id(v))
+        .map(str::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let span = tracing::info_span!("request", request_id = %id);
+
+    let mut res = next.call(req).instrument(span).await?;
+    res.headers_mut().insert(REQUEST_ID_HEADER, HeaderValue::from_str(&id)?);
+    Ok(res.map_into_boxed_body())
+}
+
+// Rejects ids that could forge log lines (newlines, spaces) or bloat them
+fn is_safe_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, middleware::from_fn};
+
+    #[actix_web::test]
+    async fn test_supplied_request_id_is_echoed() {
+        // A well-formed client id is kept and returned unchanged.
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(request_id))
+                .route("/", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((REQUEST_ID_HEADER, "abc-123"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+    }
+
+    #[actix_web::test]
+    async fn test_missing_request_id_is_generated() {
+        // Without a header, a fresh UUID is generated and echoed.
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(request_id))
+                .route("/", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        let id = resp.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_unsafe_request_id_is_replaced() {
+        // An id that could inject log lines is discarded in favour of a UUID.
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(request_id))
+                .route("/", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((REQUEST_ID_HEADER, "id with spaces"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let id = resp.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+    }
+}
@@ -0,0 +1,72 @@
+// Per-tenant redirect allow-lists for multi-tenant deployments
+use std::collections::HashMap;
+
+use url::Url;
+
+use crate::{RedirectError, RedirectPolicy};
+
+/// Maps tenant ids to their own `RedirectPolicy`.
+/// Unknown tenants get no policy at all (fail closed), never a shared default:
+/// one tenant's trusted hosts must not become redirect targets for another
+#[derive(Debug, Clone, Default)]
+pub struct TenantRedirectPolicies {
+    policies: HashMap<String, RedirectPolicy>,
+}
+
+impl TenantRedirectPolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers (or replaces) the policy for `tenant_id`
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>, policy: RedirectPolicy) -> Self {
+        self.policies.insert(tenant_id.into(), policy);
+        self
+    }
+
+    /// The policy for `tenant_id`, or None if the tenant isn't configured
+    pub fn policy_for(&self, tenant_id: &str) -> Option<&RedirectPolicy> {
+        self.policies.get(tenant_id)
+    }
+
+    /// Validates `input` against the tenant's own allow-list.
+    /// An unconfigured tenant is treated as having an empty one
+    pub fn validate(&self, tenant_id: &str, input: &str) -> Result<Url, RedirectError> {
+        match self.policy_for(tenant_id) {
+            Some(policy) => policy.validate(input),
+            None => {
+                tracing::warn!(target: "redirect", "Redirect for unknown tenant {:?} refused", tenant_id);
+                Err(RedirectError::UntrustedDomain)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policies() -> TenantRedirectPolicies {
+        TenantRedirectPolicies::new()
+            .with_tenant("acme", RedirectPolicy::new(["acme.com", "*.acme.com"]))
+            .with_tenant("globex", RedirectPolicy::new(["globex.com"]))
+    }
+
+    #[test]
+    fn test_each_tenant_uses_its_own_allow_list() {
+        // A host trusted by one tenant is untrusted for the other.
+        let policies = policies();
+        assert!(policies.validate("acme", "https://app.acme.com/home").is_ok());
+        assert!(policies.validate("globex", "https://globex.com/home").is_ok());
+        assert_eq!(policies.validate("acme", "https://globex.com/home"), Err(RedirectError::UntrustedDomain));
+        assert_eq!(policies.validate("globex", "https://app.acme.com/home"), Err(RedirectError::UntrustedDomain));
+    }
+
+    #[test]
+    fn test_unknown_tenant_fails_closed() {
+        // No configured policy means nothing is allowed, not the default allow-list.
+        let policies = policies();
+        assert!(policies.policy_for("initech").is_none());
+        assert_eq!(policies.validate("initech", "https://trusted.com/"), Err(RedirectError::UntrustedDomain));
+    }
+}
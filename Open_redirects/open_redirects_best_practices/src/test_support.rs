@@ -0,0 +1,52 @@
+// Helpers shared by the crate's unit tests
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::layer::SubscriberExt;
+
+// Collects everything the fmt layer writes
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Captures this thread's logs until dropped, as `LEVEL target: message fields` lines.
+/// A guard rather than a closure, so it also covers `.await`s in actix tests.
+pub(crate) struct LogCapture {
+    captured: Captured,
+    _default: DefaultGuard,
+}
+
+impl LogCapture {
+    pub(crate) fn start() -> Self {
+        let captured = Captured::default();
+        let sink = captured.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer().without_time().with_ansi(false).with_writer(move || sink.clone()),
+        );
+        Self { captured, _default: tracing::subscriber::set_default(subscriber) }
+    }
+
+    pub(crate) fn output(&self) -> String {
+        String::from_utf8(self.captured.0.lock().unwrap().clone()).unwrap()
+    }
+
+    /// The target of each captured line, so tests can check how logs will be routed
+    pub(crate) fn targets(&self) -> Vec<String> {
+        let output = self.output();
+        output
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .map(|target| target.trim_end_matches(':').to_string())
+            .collect()
+    }
+}
@@ -0,0 +1,310 @@
+// Storage for the token -> URL map behind `token_redirect`
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+use crate::clock::{Clock, SystemClock};
+
+type TokenDigest = [u8; 32];
+
+/// Backend for redirect tokens. `token_redirect` only ever calls `redeem`.
+///
+/// To share tokens across nodes and restarts, implement it over:
+/// - Redis: `get` = `GET redirect:{token}`, `put` = `SET redirect:{token} url PX ttl_ms`
+///   (plain `SET` without a TTL), `remove` = `DEL`; Redis expires entries itself.
+///   For `put_limited`, also `SET redirect:{token}:uses max_uses` and make `redeem` a
+///   Lua script that `DECR`s it and refuses at zero, so nodes can't race past the limit.
+/// - Postgres: a `redirect_tokens(token TEXT PRIMARY KEY, url TEXT NOT NULL, expires_at TIMESTAMPTZ,
+///   uses_left INT)` table; `get` filters on `expires_at IS NULL OR expires_at > now()`, and
+///   `redeem` is `UPDATE ... SET uses_left = uses_left - 1 WHERE ... AND uses_left > 0 RETURNING url`.
+///
+/// Calls are synchronous; a networked backend should use a blocking client or a local cache.
+pub trait TokenStore: Send + Sync {
+    /// Target URL for `token`, or None if unknown, expired or used up. Doesn't count as a use
+    fn get(&self, token: &str) -> Option<String>;
+    /// Stores `url` under `token`, replacing any previous entry; `ttl` of None never expires
+    fn put(&self, token: &str, url: &str, ttl: Option<Duration>);
+    /// Like `put`, but the token stops working after `max_uses` redemptions (or `ttl`, whichever comes first).
+    /// The default stores nothing and returns `UsageLimitsUnsupported`: falling back to `put` would
+    /// quietly turn a single-use token into a reusable one. Override it together with `redeem`
+    fn put_limited(
+        &self,
+        _token: &str,
+        _url: &str,
+        _ttl: Option<Duration>,
+        _max_uses: u32,
+    ) -> Result<(), UsageLimitsUnsupported> {
+        Err(UsageLimitsUnsupported)
+    }
+    /// Deletes `token`; returns whether it was present
+    fn remove(&self, token: &str) -> bool;
+
+    /// Looks `token` up and counts one use against its limit.
+    /// The default suits stores without usage limits: it just calls `get`
+    fn redeem(&self, token: &str) -> Redemption {
+        self.get(token).map_or(Redemption::Unknown, Redemption::Valid)
+    }
+}
+
+/// Returned by `TokenStore::put_limited` on backends that can't count uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsageLimitsUnsupported;
+
+impl fmt::Display for UsageLimitsUnsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Token store does not support usage-limited tokens")
+    }
+}
+
+impl std::error::Error for UsageLimitsUnsupported {}
+
+/// Outcome of `TokenStore::redeem`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redemption {
+    Valid(String), // Target URL; one use has been counted
+    Exhausted,     // Known token whose `max_uses` are spent (410 Gone)
+    Unknown,       // Never issued, removed or expired
+}
+
+/// Process-local `TokenStore`; the default backend.
+/// Tokens are kept as SHA-256 digests and `get` compares the presented token against
+/// every entry in constant time, so lookup timing doesn't reveal how close a guess was.
+#[derive(Debug, Default)]
+pub struct InMemoryTokenStore<C: Clock = SystemClock> {
+    entries: Mutex<HashMap<TokenDigest, Entry>>, // Keyed by sha256(token)
+    clock: C,
+}
+
+#[derive(Debug)]
+struct Entry {
+    url: String,
+    expires_at: Option<u64>,
+    uses_left: Option<u32>, // None: unlimited
+}
+
+impl InMemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C: Clock> InMemoryTokenStore<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Self { entries: Mutex::new(HashMap::new()), clock }
+    }
+}
+
+impl<const N: usize> From<[(&str, &str); N]> for InMemoryTokenStore {
+    /// Non-expiring tokens, e.g. a fixed set of named destinations
+    fn from(entries: [(&str, &str); N]) -> Self {
+        let store = Self::new();
+        for (token, url) in entries {
+            store.put(token, url, None);
+        }
+        store
+    }
+}
+
+impl<C: Clock> InMemoryTokenStore<C> {
+    // Runs `f` on the live entry for `token`; expired entries are dropped so the map doesn't grow forever
+    fn with_entry<T>(&self, token: &str, f: impl FnOnce(&mut Entry) -> T) -> Option<T> {
+        let now = self.clock.unix_secs();
+        let mut entries = self.entries.lock().unwrap();
+        let (found, _) = scan_constant_time(&entries, &digest(token));
+        let found = found?;
+        if entries[&found].expires_at.is_some_and(|expires_at| expires_at <= now) {
+            entries.remove(&found);
+            return None;
+        }
+        entries.get_mut(&found).map(f)
+    }
+
+    fn insert(&self, token: &str, url: &str, ttl: Option<Duration>, uses_left: Option<u32>) {
+        let expires_at = ttl.map(|ttl| self.clock.unix_secs() + ttl.as_secs());
+        let entry = Entry { url: url.to_string(), expires_at, uses_left };
+        self.entries.lock().unwrap().insert(digest(token), entry);
+    }
+}
+
+impl<C: Clock> TokenStore for InMemoryTokenStore<C> {
+    fn get(&self, token: &str) -> Option<String> {
+        self.with_entry(token, |entry| (entry.uses_left != Some(0)).then(|| entry.url.clone())).flatten()
+    }
+
+    fn put(&self, token: &str, url: &str, ttl: Option<Duration>) {
+        self.insert(token, url, ttl, None);
+    }
+
+    fn put_limited(
+        &self,
+        token: &str,
+        url: &str,
+        ttl: Option<Duration>,
+        max_uses: u32,
+    ) -> Result<(), UsageLimitsUnsupported> {
+        self.insert(token, url, ttl, Some(max_uses));
+        Ok(())
+    }
+
+    fn remove(&self, token: &str) -> bool {
+        self.entries.lock().unwrap().remove(&digest(token)).is_some()
+    }
+
+    // Spent tokens stay in the map (until their TTL, if any) so they keep answering Exhausted
+    fn redeem(&self, token: &str) -> Redemption {
+        let redemption = self.with_entry(token, |entry| match &mut entry.uses_left {
+            Some(0) => Redemption::Exhausted,
+            Some(uses_left) => {
+                *uses_left -= 1;
+                Redemption::Valid(entry.url.clone())
+            }
+            None => Redemption::Valid(entry.url.clone()),
+        });
+        redemption.unwrap_or(Redemption::Unknown)
+    }
+}
+
+fn digest(token: &str) -> TokenDigest {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+// Compares `wanted` with every key, never breaking early; also returns how many keys were compared
+fn scan_constant_time<V>(entries: &HashMap<TokenDigest, V>, wanted: &TokenDigest) -> (Option<TokenDigest>, usize) {
+    let mut found = [0u8; 32];
+    let mut any = subtle::Choice::from(0);
+    let mut compared = 0;
+    for key in entries.keys() {
+        let hit = key.ct_eq(wanted);
+        for (out, byte) in found.iter_mut().zip(key) {
+            out.conditional_assign(byte, hit);
+        }
+        any |= hit;
+        compared += 1;
+    }
+    (bool::from(any).then_some(found), compared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use actix_web::{web, App, http::{header, StatusCode}};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use std::sync::Arc;
+
+    // Records every key `get` is asked for and knows a single token
+    #[derive(Default)]
+    struct MockTokenStore {
+        lookups: Mutex<Vec<String>>,
+    }
+
+    impl TokenStore for MockTokenStore {
+        fn get(&self, token: &str) -> Option<String> {
+            self.lookups.lock().unwrap().push(token.to_string());
+            (token == "profile").then(|| "https://trusted.com/me".to_string())
+        }
+        fn put(&self, _token: &str, _url: &str, _ttl: Option<Duration>) {}
+        fn remove(&self, _token: &str) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_present_absent_and_expired_tokens() {
+        // Stored tokens resolve until their TTL passes; unknown and removed ones never do.
+        let clock = MockClock::default();
+        let store = InMemoryTokenStore::with_clock(clock.clone());
+        store.put("dashboard", "https://trusted.com/dash", None);
+        store.put("invite", "https://trusted.com/join", Some(Duration::from_secs(60)));
+
+        assert_eq!(store.get("dashboard").as_deref(), Some("https://trusted.com/dash"));
+        assert_eq!(store.get("invite").as_deref(), Some("https://trusted.com/join"));
+        assert_eq!(store.get("unknown"), None);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(store.get("invite"), None);
+        assert_eq!(store.get("dashboard").as_deref(), Some("https://trusted.com/dash"));
+
+        assert!(store.remove("dashboard"));
+        assert!(!store.remove("dashboard"));
+        assert_eq!(store.get("dashboard"), None);
+    }
+
+    #[test]
+    fn test_put_limited_unsupported_by_default() {
+        // A store without usage counting refuses limited tokens instead of storing them unlimited.
+        let store = MockTokenStore::default();
+        assert_eq!(store.put_limited("reset", "https://trusted.com/reset", None, 1), Err(UsageLimitsUnsupported));
+    }
+
+    #[actix_web::test]
+    async fn test_limited_tokens_gone_after_max_uses() {
+        // A single-use token redirects once, then 410; a 3-use token works 3 times; the TTL still applies.
+        let clock = MockClock::default();
+        let store = Arc::new(InMemoryTokenStore::with_clock(clock.clone()));
+        store.put_limited("once", "https://trusted.com/reset", None, 1).unwrap();
+        store.put_limited("thrice", "https://trusted.com/share", None, 3).unwrap();
+        store.put_limited("timed", "https://trusted.com/offer", Some(Duration::from_secs(60)), 5).unwrap();
+        let dyn_store: Arc<dyn TokenStore> = store.clone();
+        let app = init_service(App::new().app_data(web::Data::from(dyn_store)).service(crate::token_redirect)).await;
+        let status = |token: &'static str| {
+            let app = &app;
+            async move {
+                let req = TestRequest::get().uri(&format!("/safe_redirect/{}", token)).to_request();
+                call_service(app, req).await.status()
+            }
+        };
+
+        assert_eq!(status("once").await, StatusCode::FOUND);
+        assert_eq!(status("once").await, StatusCode::GONE);
+        assert_eq!(store.get("once"), None);
+        for _ in 0..3 {
+            assert_eq!(status("thrice").await, StatusCode::FOUND);
+        }
+        assert_eq!(status("thrice").await, StatusCode::GONE);
+
+        assert_eq!(status("timed").await, StatusCode::FOUND);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(status("timed").await, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_lookup_scans_every_entry() {
+        // Valid and invalid tokens both compare against all entries; neither exits early.
+        let store = InMemoryTokenStore::from([
+            ("dashboard", "https://trusted.com/dash"),
+            ("profile", "https://trusted.com/me"),
+            ("billing", "https://trusted.com/billing"),
+        ]);
+        let entries = store.entries.lock().unwrap();
+        let (hit, compared_valid) = scan_constant_time(&entries, &digest("profile"));
+        assert_eq!(hit, Some(digest("profile")));
+        let (miss, compared_invalid) = scan_constant_time(&entries, &digest("profilf"));
+        assert_eq!(miss, None);
+        assert_eq!(compared_valid, 3);
+        assert_eq!(compared_invalid, 3);
+    }
+
+    #[actix_web::test]
+    async fn test_handler_looks_up_path_token() {
+        // token_redirect asks the configured store for exactly the token in the path.
+        let mock = Arc::new(MockTokenStore::default());
+        let store: Arc<dyn TokenStore> = mock.clone();
+        let app = init_service(
+            App::new().app_data(web::Data::from(store)).service(crate::token_redirect),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/safe_redirect/profile").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), "https://trusted.com/me");
+        let resp = call_service(&app, TestRequest::get().uri("/safe_redirect/nope").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        assert_eq!(*mock.lookups.lock().unwrap(), ["profile", "nope"]);
+    }
+}
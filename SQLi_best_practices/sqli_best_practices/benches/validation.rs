@@ -0,0 +1,94 @@
+//! Performance baseline for the SQLi checks. Run with `cargo bench --bench validation`.
+//!
+//! Targets on a modern x86-64 core (release build); a PR that pushes a number
+//! past its target should say why in the description:
+//!
+//! | Benchmark                              | Target    |
+//! |----------------------------------------|-----------|
+//! | `validate_input/benign_short`          | < 2 µs    |
+//! | `validate_input/malicious_short`       | < 2 µs    |
+//! | `validate_long/benign_4k`              | < 100 µs  |
+//! | `validate_long/malicious_4k`           | < 100 µs  |
+//! | `analyze_query/*`                      | < 1 µs    |
+//! | `regex_compile/compiled_once`          | < 2 µs    |
+//! | `regex_compile/recompiled_per_call`    | > 100x `compiled_once`, which is why rules live in a `LazyLock` |
+//! | `allow_list/hash_set/*`                | flat as the list grows |
+
+use std::collections::HashSet;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sqli_best_practices::{analyze_query, validate_input, SqliValidator, SqliValidatorConfig};
+
+const BENIGN_SHORT: &str = "alice.smith";
+const MALICIOUS_SHORT: &str = "' OR 1=1 --";
+
+fn bench_validate_input(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate_input");
+    group.bench_function("benign_short", |b| b.iter(|| validate_input(black_box(BENIGN_SHORT))));
+    group.bench_function("malicious_short", |b| b.iter(|| validate_input(black_box(MALICIOUS_SHORT))));
+    group.finish();
+}
+
+// `validate_input` caps inputs at 100 chars, so long inputs need a validator with a higher limit
+fn bench_validate_long(c: &mut Criterion) {
+    let validator = SqliValidator::new(SqliValidatorConfig { max_len: 8 * 1024, ..Default::default() }).unwrap();
+    let benign = "lorem ipsum dolor sit amet ".repeat(150);
+    let malicious = format!("{}' UNION SELECT password FROM users --", &benign[..4000]);
+
+    let mut group = c.benchmark_group("validate_long");
+    group.throughput(Throughput::Bytes(benign.len() as u64));
+    group.bench_function("benign_4k", |b| b.iter(|| validator.validate(black_box(&benign))));
+    group.bench_function("malicious_4k", |b| b.iter(|| validator.validate(black_box(&malicious))));
+    group.finish();
+}
+
+fn bench_analyze_query(c: &mut Criterion) {
+    let queries = [
+        ("plain", "SELECT id, username FROM users WHERE id = $1"),
+        ("union", "SELECT id FROM users WHERE id = 1 UNION SELECT password FROM admins"),
+    ];
+    let mut group = c.benchmark_group("analyze_query");
+    for (name, query) in queries {
+        group.throughput(Throughput::Bytes(query.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), query, |b, q| b.iter(|| analyze_query(black_box(q))));
+    }
+    group.finish();
+}
+
+// What the compile-once refactor buys: the shared validator versus building the rule set per call
+fn bench_regex_compile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("regex_compile");
+    group.bench_function("compiled_once", |b| b.iter(|| validate_input(black_box(BENIGN_SHORT))));
+    group.bench_function("recompiled_per_call", |b| {
+        b.iter(|| SqliValidator::default().validate(black_box(BENIGN_SHORT)))
+    });
+    group.finish();
+}
+
+// `safe_order_by` scans its slice; fine for a handful of columns, not for large allow-lists
+fn bench_allow_list(c: &mut Criterion) {
+    let mut group = c.benchmark_group("allow_list");
+    for size in [10, 100, 1000] {
+        let entries: Vec<String> = (0..size).map(|i| format!("column_{i}")).collect();
+        let set: HashSet<&str> = entries.iter().map(String::as_str).collect();
+        let missing = "not_a_column"; // Worst case for the linear scan
+        group.bench_with_input(BenchmarkId::new("linear", size), &entries, |b, entries| {
+            b.iter(|| entries.iter().any(|e| e == black_box(missing)))
+        });
+        group.bench_with_input(BenchmarkId::new("hash_set", size), &set, |b, set| {
+            b.iter(|| set.contains(black_box(missing)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_validate_input,
+    bench_validate_long,
+    bench_analyze_query,
+    bench_regex_compile,
+    bench_allow_list
+);
+criterion_main!(benches);
@@ -0,0 +1 @@
+-- Nothing to undo: the table belongs to 2025-05-19-205523_create_users
@@ -0,0 +1,6 @@
+-- Idempotent form of the table from 2025-05-19-205523_create_users; run_migrations applies it on every startup
+CREATE TABLE IF NOT EXISTS users (
+    id SERIAL PRIMARY KEY,
+    username VARCHAR(50) UNIQUE NOT NULL,
+    email VARCHAR(255) UNIQUE NOT NULL
+);
@@ -0,0 +1 @@
+-- Nothing to undo: the function is the one 2025-05-19-205523_create_users creates
@@ -0,0 +1,12 @@
+-- Stored procedure called by create_user_sqlx; expects the users table to exist
+CREATE OR REPLACE FUNCTION create_user(uname VARCHAR, em VARCHAR)
+RETURNS users AS $$
+DECLARE
+    new_user users;
+BEGIN
+    INSERT INTO users(username, email)
+    VALUES (uname, em)
+    RETURNING * INTO new_user;
+    RETURN new_user;
+END;
+$$ LANGUAGE plpgsql;
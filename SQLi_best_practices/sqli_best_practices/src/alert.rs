@@ -0,0 +1,196 @@
+//! Real-time alerting for risky queries.
+//!
+//! `analyze_query` only writes a log line, which nobody sees until they go
+//! looking. `monitor_query` scores a query with `assess_query` and, when the
+//! score passes a threshold, hands the `QueryRisk` to an `Alerter`, such as a
+//! `WebhookAlerter` posting to a chat or incident system (`webhook` feature).
+
+use std::future::Future;
+
+use crate::{assess_query, QueryRisk};
+
+/// Scores at or above this are sent to the alerter by default (High severity).
+pub const DEFAULT_ALERT_THRESHOLD: u32 = 70;
+
+/// Destination for high-risk query alerts. Implementations may write
+/// `async fn alert(&self, risk: &QueryRisk)`; failures should be logged, not raised,
+/// so a broken alert channel never fails the request being monitored.
+pub trait Alerter {
+    fn alert(&self, risk: &QueryRisk) -> impl Future<Output = ()> + Send;
+}
+
+/// Assesses `query` and alerts when its score reaches `threshold`; returns the assessment either way.
+pub async fn monitor_query<A: Alerter>(query: &str, alerter: &A, threshold: u32) -> QueryRisk {
+    let risk = assess_query(query);
+    if risk.score >= threshold {
+        tracing::warn!(target: "sqli", score = risk.score, signals = ?risk.signals, "High-risk query, alerting");
+        alerter.alert(&risk).await;
+    }
+    risk
+}
+
+#[cfg(feature = "webhook")]
+pub use webhook::{WebhookAlerter, WebhookError};
+
+#[cfg(feature = "webhook")]
+mod webhook {
+    use std::fmt;
+    use std::time::Duration;
+
+    use reqwest::Url;
+
+    use super::Alerter;
+    use crate::QueryRisk;
+
+    /// Why a webhook URL was refused.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum WebhookError {
+        InvalidUrl,
+        /// Only `http` and `https` endpoints can be called.
+        DisallowedScheme,
+        /// `user:pass@` in the URL; put credentials in a header instead.
+        EmbeddedCredentials,
+        /// Host isn't on the allow-list passed to `WebhookAlerter::new`.
+        UntrustedHost,
+    }
+
+    impl fmt::Display for WebhookError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Self::InvalidUrl => write!(f, "Malformed webhook URL"),
+                Self::DisallowedScheme => write!(f, "Webhook URL scheme not allowed"),
+                Self::EmbeddedCredentials => write!(f, "Credentials in webhook URL not allowed"),
+                Self::UntrustedHost => write!(f, "Webhook host not in allow-list"),
+            }
+        }
+    }
+
+    impl std::error::Error for WebhookError {}
+
+    /// Posts each alert as JSON (`{"score", "severity", "signals", "query"}`) to a fixed URL.
+    /// The URL is checked against an allow-list of hosts when the alerter is built, the
+    /// same exact-host check the redirect allow-list applies, so a misconfigured or
+    /// attacker-influenced URL can't aim the server at internal services (SSRF).
+    #[derive(Debug, Clone)]
+    pub struct WebhookAlerter {
+        client: reqwest::Client,
+        url: Url,
+    }
+
+    impl WebhookAlerter {
+        pub fn new(url: &str, allowed_hosts: &[&str]) -> Result<Self, WebhookError> {
+            let url = Url::parse(url).map_err(|_| WebhookError::InvalidUrl)?;
+            if !matches!(url.scheme(), "https" | "http") {
+                return Err(WebhookError::DisallowedScheme);
+            }
+            if !url.username().is_empty() || url.password().is_some() {
+                return Err(WebhookError::EmbeddedCredentials);
+            }
+            let host = url.host_str().unwrap_or("");
+            if !allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)) {
+                return Err(WebhookError::UntrustedHost);
+            }
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .redirect(reqwest::redirect::Policy::none()) // A redirect could leave the allow-list
+                .build()
+                .expect("webhook client settings are valid");
+            Ok(Self { client, url })
+        }
+    }
+
+    impl Alerter for WebhookAlerter {
+        async fn alert(&self, risk: &QueryRisk) {
+            let payload = serde_json::json!({
+                "score": risk.score,
+                "severity": risk.severity(),
+                "signals": risk.signals,
+                "query": risk.canonical,
+            });
+            let result = self.client.post(self.url.clone()).json(&payload).send().await;
+            match result.and_then(|resp| resp.error_for_status()) {
+                Ok(_) => tracing::debug!(target: "sqli", score = risk.score, "Query risk alert delivered"),
+                Err(e) => tracing::error!(target: "sqli", error = %e, "Failed to deliver query risk alert"),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "webhook"))]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Minimal HTTP server: records each request body and answers 204
+    async fn mock_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://127.0.0.1:{}/hooks/sqli", listener.local_addr().unwrap().port());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let recorded = bodies.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Read until the body announced by Content-Length has arrived
+                let body = loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let len = head
+                            .to_ascii_lowercase()
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:")?.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if body.len() >= len {
+                            break body.to_string();
+                        }
+                    }
+                };
+                recorded.lock().unwrap().push(body);
+                stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+            }
+        });
+        (url, bodies)
+    }
+
+    #[tokio::test]
+    async fn test_high_risk_posts_webhook_low_risk_does_not() {
+        // Only the query over the threshold reaches the endpoint, as JSON.
+        let (url, bodies) = mock_server().await;
+        let alerter = WebhookAlerter::new(&url, &["127.0.0.1"]).unwrap();
+
+        let low = monitor_query("SELECT id FROM users -- list page", &alerter, DEFAULT_ALERT_THRESHOLD).await;
+        assert!(low.score < DEFAULT_ALERT_THRESHOLD);
+        assert!(bodies.lock().unwrap().is_empty());
+
+        let query = "SELECT 1 WHERE 1=1 OR 1=1 UNION SELECT password FROM users";
+        let high = monitor_query(query, &alerter, DEFAULT_ALERT_THRESHOLD).await;
+        assert!(high.score >= DEFAULT_ALERT_THRESHOLD);
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 1);
+        let payload: serde_json::Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert_eq!(payload["severity"], "high");
+        assert_eq!(payload["score"], high.score);
+    }
+
+    #[test]
+    fn test_webhook_url_must_be_allow_listed() {
+        // Internal and unlisted endpoints are refused before any request is made.
+        let allowed = ["hooks.example.com"];
+        assert!(WebhookAlerter::new("https://hooks.example.com/sqli", &allowed).is_ok());
+        assert_eq!(
+            WebhookAlerter::new("http://169.254.169.254/latest/meta-data", &allowed).unwrap_err(),
+            WebhookError::UntrustedHost
+        );
+        assert_eq!(WebhookAlerter::new("file:///etc/passwd", &allowed).unwrap_err(), WebhookError::DisallowedScheme);
+        assert_eq!(
+            WebhookAlerter::new("https://a:b@hooks.example.com/", &allowed).unwrap_err(),
+            WebhookError::EmbeddedCredentials
+        );
+        assert_eq!(WebhookAlerter::new("not a url", &allowed).unwrap_err(), WebhookError::InvalidUrl);
+    }
+}
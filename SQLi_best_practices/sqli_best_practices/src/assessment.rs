@@ -0,0 +1,137 @@
+//! One-call risk verdict for an untrusted input.
+//!
+//! `assess_input` runs a value through every check the crate has (the default
+//! validator, the query risk signals, the identifier rules and a few encoding
+//! tricks) and folds the results into a single `RiskAssessment`. Scanners and
+//! WAF-style integrations get one score and one block decision, plus the list
+//! of signals that explain it.
+
+use serde::Serialize;
+
+use crate::validator::{is_bidi_control, validate_identifier, ValidationError};
+use crate::{canonicalize_query, query_signals, DEFAULT_VALIDATOR};
+
+/// `total_score` at or above which an input is blocked even if no single layer rejected it.
+pub const BLOCK_SCORE: u8 = 70;
+
+/// Which check produced a `Signal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalLayer {
+    /// The default `SqliValidator` (`validate_input`).
+    Validation,
+    /// The weighted query signals behind `assess_query`.
+    QueryAnalysis,
+    /// Characters that would break out of a quoted identifier (see `validate_identifier`).
+    Identifier,
+    /// Hidden or look-alike characters: bidi controls, control bytes, fullwidth quotes.
+    Encoding,
+}
+
+/// One reason an input looked risky.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Signal {
+    pub layer: SignalLayer,
+    /// Rule or signal name, e.g. `union-select`, `bidi-control`.
+    pub name: &'static str,
+    /// Contribution to `RiskAssessment::total_score`.
+    pub score: u8,
+}
+
+/// Composite verdict from `assess_input`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RiskAssessment {
+    /// True if the validator rejected the input or `total_score` reached `BLOCK_SCORE`.
+    pub blocked: bool,
+    /// 0-100; the sum of every signal's score, capped at 100.
+    pub total_score: u8,
+    pub signals: Vec<Signal>,
+}
+
+// Fullwidth ' " ; - : NFKC normalization (or a lenient collation) turns them into the ASCII metacharacters
+fn is_fullwidth_meta(c: char) -> bool {
+    matches!(c, '\u{FF07}' | '\u{FF02}' | '\u{FF1B}' | '\u{FF0D}')
+}
+
+/// Runs `input` through every layer and returns the combined verdict.
+/// Why: Each layer alone misses something (the validator stops at the first rule,
+/// `assess_query` ignores encoding); integrations want one answer with the reasons attached.
+/// Empty input is not an attack and scores zero.
+pub fn assess_input(input: &str) -> RiskAssessment {
+    let mut signals = Vec::new();
+    let mut rejected = false;
+
+    match DEFAULT_VALIDATOR.validate(input) {
+        Ok(()) | Err(ValidationError::EmptyInput) => {}
+        Err(ValidationError::SqlPatternDetected { rule, .. }) => {
+            rejected = true;
+            signals.push(Signal { layer: SignalLayer::Validation, name: rule, score: 50 });
+        }
+        Err(ValidationError::TooLong { .. }) => {
+            signals.push(Signal { layer: SignalLayer::Validation, name: "too_long", score: 10 });
+        }
+        // Too large, out of budget or strict-mode bidi: the validator refused to vouch for it
+        Err(e) => {
+            rejected = true;
+            signals.push(Signal { layer: SignalLayer::Validation, name: e.code(), score: 30 });
+        }
+    }
+
+    let canonical = canonicalize_query(input);
+    for (name, weight) in query_signals(input, &canonical) {
+        signals.push(Signal { layer: SignalLayer::QueryAnalysis, name, score: weight.min(100) as u8 });
+    }
+
+    if validate_identifier(input).is_err() && input.contains(['"', '`']) {
+        signals.push(Signal { layer: SignalLayer::Identifier, name: "identifier-quote", score: 30 });
+    }
+
+    if input.contains(is_bidi_control) {
+        signals.push(Signal { layer: SignalLayer::Encoding, name: "bidi-control", score: 30 });
+    }
+    if input.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')) {
+        signals.push(Signal { layer: SignalLayer::Encoding, name: "control-character", score: 30 });
+    }
+    if input.contains(is_fullwidth_meta) {
+        signals.push(Signal { layer: SignalLayer::Encoding, name: "fullwidth-metachar", score: 30 });
+    }
+
+    let total_score = signals.iter().map(|s| u32::from(s.score)).sum::<u32>().min(100) as u8;
+    RiskAssessment { blocked: rejected || total_score >= BLOCK_SCORE, total_score, signals }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benign_input_scores_zero() {
+        // Ordinary names and emails raise nothing; blank input isn't an attack either.
+        for input in ["alice", "Jane Doe", "alice@example.com", ""] {
+            let assessment = assess_input(input);
+            assert_eq!(assessment, RiskAssessment { blocked: false, total_score: 0, signals: vec![] }, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_multi_vector_payload_aggregates_and_blocks() {
+        // Union, tautology, comment and a hidden bidi override each add a signal from their own layer.
+        let assessment = assess_input("1' OR '1'='1' UNION SELECT password FROM users--\u{202E}");
+        assert!(assessment.blocked);
+        assert_eq!(assessment.total_score, 100);
+        let layers: Vec<_> = assessment.signals.iter().map(|s| s.layer).collect();
+        assert!(layers.contains(&SignalLayer::Validation));
+        assert!(layers.contains(&SignalLayer::QueryAnalysis));
+        assert!(layers.contains(&SignalLayer::Encoding));
+        let names: Vec<_> = assessment.signals.iter().map(|s| s.name).collect();
+        for expected in ["union-select", "tautology", "comment", "bidi-control"] {
+            assert!(names.contains(&expected), "missing {} in {:?}", expected, names);
+        }
+
+        // A fullwidth quote is folded to `'` by the validator and also flagged as a look-alike
+        let encoded = assess_input("admin\u{FF07}");
+        let names: Vec<_> = encoded.signals.iter().map(|s| (s.layer, s.name)).collect();
+        assert_eq!(names, [(SignalLayer::Validation, "quote"), (SignalLayer::Encoding, "fullwidth-metachar")]);
+        assert!(encoded.blocked);
+    }
+}
@@ -0,0 +1,131 @@
+//! Async Diesel (`async-diesel` feature): a bb8 pool of `AsyncPgConnection`s.
+//!
+//! The sync Diesel helpers block a runtime thread for every query; these run on
+//! the same tokio runtime as the SQLx code. They validate like their sync
+//! counterparts and insert through `logged_transaction_async`, so a failed insert
+//! rolls back completely and the outcome is logged the same way.
+//!
+//! Connections go through tokio-postgres with native-tls, verifying the server
+//! certificate and hostname against the system roots. tokio-postgres defaults to
+//! `sslmode=prefer`, which silently falls back to plaintext, so `prefer` (stated or
+//! implied) is upgraded to `require`; only an explicit `sslmode=disable` connects
+//! without TLS, e.g. for a local dev database.
+
+use std::error::Error;
+use std::fmt;
+
+use diesel::{ConnectionError, ConnectionResult};
+use diesel_async::pooled_connection::bb8::Pool;
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig, PoolError};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncPgConnection;
+use futures_util::FutureExt;
+use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::config::SslMode;
+
+use crate::tx::logged_transaction_async;
+use crate::{canonicalize_email, require_non_blank, DieselUser, NewDieselUser};
+
+/// bb8 pool of async Diesel connections, as built by `create_diesel_async_pool`.
+pub type DieselAsyncPool = Pool<AsyncPgConnection>;
+
+/// Creates a pool of up to `max_size` async ORM connections.
+/// Why: Same reasoning as `create_diesel_pool`, without tying up a thread per query;
+/// each connection is checked on checkout so dropped ones are replaced.
+/// TLS is required unless the URL says `sslmode=disable` (see the module docs).
+pub async fn create_diesel_async_pool(db_url: &str, max_size: u32) -> Result<DieselAsyncPool, PoolError> {
+    let mut config = ManagerConfig::default();
+    config.custom_setup = Box::new(|url| establish_tls(url).boxed());
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(db_url, config);
+    DieselAsyncPool::builder().max_size(max_size).test_on_check_out(true).build(manager).await
+}
+
+// One pooled connection: native-tls, with `prefer` upgraded so a plaintext-only server is refused
+async fn establish_tls(db_url: &str) -> ConnectionResult<AsyncPgConnection> {
+    let mut config: tokio_postgres::Config = db_url.parse().map_err(bad_connection)?;
+    if config.get_ssl_mode() == SslMode::Prefer {
+        config.ssl_mode(SslMode::Require);
+    }
+    let tls = MakeTlsConnector::new(native_tls::TlsConnector::new().map_err(bad_connection)?);
+    let (client, connection) = config.connect(tls).await.map_err(bad_connection)?;
+    AsyncPgConnection::try_from_client_and_connection(client, connection).await
+}
+
+fn bad_connection(e: impl fmt::Display) -> ConnectionError {
+    ConnectionError::BadConnection(e.to_string())
+}
+
+/// Async `create_user_diesel`: same validation, one all-or-nothing transaction, outcome logged.
+/// Pass a pooled connection with `&mut pool.get().await?`.
+pub async fn create_user_diesel_async(
+    conn: &mut AsyncPgConnection,
+    username: &str,
+    email: &str,
+) -> Result<DieselUser, Box<dyn Error>> {
+    require_non_blank(username)?; // Reject blank rows before opening a transaction
+    let email = canonicalize_email(email)?;
+
+    logged_transaction_async(conn, "create_user_diesel_async", |tx| {
+        async move {
+            let new_user = NewDieselUser { username, email: &email }; // id assigned by the DB
+            let query = diesel::insert_into(crate::schema::users::table).values(&new_user);
+            Ok(diesel_async::RunQueryDsl::get_result(query, tx).await?)
+        }
+        .scope_boxed()
+    })
+    .await
+    .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::users::dsl::{id, users};
+    use diesel::prelude::*;
+    use diesel_async::{AsyncConnection, RunQueryDsl};
+
+    #[tokio::test]
+    async fn test_plaintext_server_refused_without_explicit_opt_out() {
+        // A server that declines TLS is dropped after the SSLRequest; no startup message, so no credentials, is sent.
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut ssl_request = [0u8; 8];
+            socket.read_exact(&mut ssl_request).await.unwrap();
+            socket.write_all(b"N").await.unwrap(); // "No TLS here"
+            let mut rest = Vec::new();
+            socket.read_to_end(&mut rest).await.unwrap();
+            (ssl_request, rest)
+        });
+
+        let Err(err) = establish_tls(&format!("postgres://app:hunter2@{addr}/app")).await else {
+            panic!("connected to a server without TLS");
+        };
+        assert!(matches!(err, ConnectionError::BadConnection(_)), "{err:?}");
+        let (ssl_request, rest) = server.await.unwrap();
+        assert_eq!(ssl_request, [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f]);
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a Postgres database at DATABASE_URL (add sslmode=disable for a server without TLS)"]
+    async fn test_async_insert_reads_back() {
+        // A user inserted through the pool reads back identically; a duplicate rolls back.
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = create_diesel_async_pool(&db_url, 2).await.unwrap();
+        let mut conn = pool.get().await.unwrap();
+        conn.begin_test_transaction().await.unwrap();
+        diesel::sql_query(crate::migrations::CREATE_USERS_TABLE_SQL).execute(&mut *conn).await.unwrap();
+
+        let created = create_user_diesel_async(&mut conn, "async_olga", "Olga@Example.com").await.unwrap();
+        let found: DieselUser = users.filter(id.eq(created.id)).first(&mut *conn).await.unwrap();
+        assert_eq!((found.username.as_str(), found.email.as_str()), ("async_olga", "olga@example.com"));
+
+        assert!(create_user_diesel_async(&mut conn, "async_olga", "olga@example.com").await.is_err());
+        let count: i64 = users.filter(id.ge(created.id)).count().get_result(&mut *conn).await.unwrap();
+        assert_eq!(count, 1);
+        assert!(create_user_diesel_async(&mut conn, " ", "olga@example.com").await.is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! In-process circuit breaker for database calls.
+//!
+//! When Postgres is down, every query waits out the pool's acquire timeout and
+//! requests pile up behind it. After `failure_threshold` consecutive failures
+//! the breaker opens and calls fail immediately with `CircuitOpen`; once the
+//! cooldown has passed, a single probe call is let through (half-open) and its
+//! outcome decides whether the circuit closes again or stays open.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Observable breaker state, for health checks and tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through; consecutive failures are counted.
+    Closed,
+    /// Calls are rejected until the cooldown ends.
+    Open,
+    /// One probe call is in flight; everything else is rejected until it finishes.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+/// Error from `CircuitBreaker::call`: either the breaker refused, or the call itself failed.
+#[derive(Debug)]
+pub enum BreakerError<E> {
+    /// The circuit is open; the call was not attempted.
+    CircuitOpen { retry_after: Duration },
+    Inner(E),
+}
+
+impl<E: fmt::Display> fmt::Display for BreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CircuitOpen { retry_after } => {
+                write!(f, "Circuit open, retry in {}ms", retry_after.as_millis())
+            }
+            Self::Inner(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for BreakerError<E> {}
+
+/// Consecutive-failure circuit breaker; share one per backend (e.g. in an `Arc`).
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: Mutex<State>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    /// Opens after `failure_threshold` consecutive failures and stays open for `cooldown`.
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(State::Closed { failures: 0 }),
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Runs `op` unless the circuit is open; every error counts as a failure.
+    pub async fn call<T, E>(&self, op: impl Future<Output = Result<T, E>>) -> Result<T, BreakerError<E>> {
+        self.call_filtered(op, |_| true).await
+    }
+
+    /// Like `call`, but only errors for which `is_failure` returns true count against the circuit.
+    /// Why: A unique violation means the database answered; only outages should trip the breaker.
+    pub async fn call_filtered<T, E>(
+        &self,
+        op: impl Future<Output = Result<T, E>>,
+        is_failure: impl Fn(&E) -> bool,
+    ) -> Result<T, BreakerError<E>> {
+        let probe = self.admit().map_err(|retry_after| BreakerError::CircuitOpen { retry_after })?;
+        let mut guard = ProbeGuard { breaker: self, armed: probe };
+        let result = op.await;
+        guard.armed = false;
+        self.record(result.as_ref().err().is_some_and(is_failure));
+        result.map_err(BreakerError::Inner)
+    }
+
+    // Ok(true) admits the half-open probe; Err carries how long until the next probe
+    fn admit(&self) -> Result<bool, Duration> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => Ok(false),
+            State::Open { until } => {
+                let now = Instant::now();
+                if now < until {
+                    return Err(until - now);
+                }
+                *state = State::HalfOpen;
+                Ok(true)
+            }
+            State::HalfOpen => Err(self.cooldown),
+        }
+    }
+
+    fn record(&self, failed: bool) {
+        let mut state = self.state.lock().unwrap();
+        if !failed {
+            *state = State::Closed { failures: 0 };
+            return;
+        }
+        let trip = match *state {
+            State::Closed { failures } if failures + 1 < self.failure_threshold => {
+                *state = State::Closed { failures: failures + 1 };
+                false
+            }
+            State::Closed { .. } | State::HalfOpen => true,
+            State::Open { .. } => false, // A straggler from before the circuit opened
+        };
+        if trip {
+            tracing::warn!(cooldown_ms = self.cooldown.as_millis() as u64, "Circuit breaker opened");
+            *state = State::Open { until: Instant::now() + self.cooldown };
+        }
+    }
+}
+
+// A cancelled probe must not leave the breaker half-open forever: let the next call probe instead
+struct ProbeGuard<'a> {
+    breaker: &'a CircuitBreaker,
+    armed: bool,
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            *self.breaker.state.lock().unwrap() = State::Open { until: Instant::now() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn fail() -> Result<(), &'static str> {
+        Err("connection refused")
+    }
+
+    #[tokio::test]
+    async fn test_failures_open_circuit_and_reject_fast() {
+        // Three failures open it; the fourth call is refused without running.
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        for _ in 0..3 {
+            assert!(matches!(breaker.call(fail()).await, Err(BreakerError::Inner(_))));
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let ran = AtomicUsize::new(0);
+        let result = breaker
+            .call(async {
+                ran.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, &str>(())
+            })
+            .await;
+        assert!(matches!(result, Err(BreakerError::CircuitOpen { retry_after }) if retry_after > Duration::from_secs(59)));
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_success_after_cooldown_closes_circuit() {
+        // After the cooldown one probe runs; success closes, failure reopens.
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(30));
+        assert!(breaker.call(fail()).await.is_err());
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(breaker.call(fail()).await.is_err()); // Failed probe
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(breaker.call(async { Ok::<_, &str>(7) }).await.unwrap(), 7);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_filtered_errors_do_not_trip() {
+        // Errors the filter ignores (e.g. duplicates) leave the circuit closed.
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        let result = breaker.call_filtered(async { Err::<(), _>("duplicate") }, |e| *e != "duplicate").await;
+        assert!(matches!(result, Err(BreakerError::Inner("duplicate"))));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,186 @@
+//! Server certificate pinning for Postgres connections.
+//!
+//! sqlx does not expose the peer certificate, so the pin is checked by a
+//! separate probe: a Postgres `SSLRequest`, a TLS handshake, and a SHA-256 of
+//! the leaf certificate's DER encoding. The probed certificate, once it
+//! matches, also becomes the pooled connection's root certificate.
+//!
+//! Compute the expected value with:
+//!
+//! ```text
+//! openssl s_client -starttls postgres -connect db.internal:5432 </dev/null 2>/dev/null \
+//!     | openssl x509 -noout -fingerprint -sha256
+//! ```
+//!
+//! and pass the `AB:CD:...` output to `parse_cert_fingerprint`.
+
+use std::error::Error;
+use std::fmt;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgSslMode;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// Postgres protocol: length 8, then the magic SSLRequest code
+const SSL_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f];
+
+/// The server presented a certificate other than the pinned one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertFingerprintMismatch {
+    pub expected: [u8; 32],
+    pub actual: [u8; 32],
+}
+
+impl fmt::Display for CertFingerprintMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Server certificate SHA-256 {} does not match pinned {}",
+            hex::encode(self.actual),
+            hex::encode(self.expected)
+        )
+    }
+}
+
+impl Error for CertFingerprintMismatch {}
+
+/// Parses a SHA-256 fingerprint as hex, with or without `:` separators
+/// (`openssl x509 -fingerprint` output, optionally prefixed `sha256 Fingerprint=`).
+pub fn parse_cert_fingerprint(input: &str) -> Option<[u8; 32]> {
+    let value = input.rsplit_once('=').map_or(input, |(_, value)| value);
+    let digits: String = value.trim().chars().filter(|c| *c != ':').collect();
+    hex::decode(digits).ok()?.try_into().ok()
+}
+
+/// Fetches the SHA-256 of the server's leaf certificate via a TLS probe.
+/// Chain and hostname checks follow `ssl_mode`: `Require` trusts the pin alone,
+/// `VerifyCa` checks the chain against system roots, `VerifyFull` also the hostname.
+pub async fn server_cert_sha256(host: &str, port: u16, ssl_mode: PgSslMode) -> Result<[u8; 32], Box<dyn Error + Send + Sync>> {
+    Ok(Sha256::digest(server_cert_der(host, port, ssl_mode).await?).into())
+}
+
+// DER encoding of the server's leaf certificate, checked as `server_cert_sha256` documents
+async fn server_cert_der(
+    host: &str,
+    port: u16,
+    ssl_mode: PgSslMode,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+    stream.write_all(&SSL_REQUEST).await?;
+    let mut answer = [0u8; 1];
+    stream.read_exact(&mut answer).await?;
+    if answer[0] != b'S' {
+        return Err("Server does not support TLS; cannot check pinned certificate".into());
+    }
+
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(!matches!(ssl_mode, PgSslMode::VerifyCa | PgSslMode::VerifyFull))
+        .danger_accept_invalid_hostnames(!matches!(ssl_mode, PgSslMode::VerifyFull))
+        .build()?;
+    let tls = tokio_native_tls::TlsConnector::from(connector).connect(host, stream).await?;
+    let cert = tls
+        .get_ref()
+        .peer_certificate()?
+        .ok_or("Server presented no certificate")?;
+    Ok(cert.to_der()?)
+}
+
+/// Probes the server and fails with `CertFingerprintMismatch` unless its
+/// certificate hashes to `expected`.
+pub async fn verify_server_cert(
+    host: &str,
+    port: u16,
+    ssl_mode: PgSslMode,
+    expected: [u8; 32],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    pinned_server_cert_pem(host, port, ssl_mode, expected).await.map(drop)
+}
+
+/// `verify_server_cert` that also returns the matching certificate as PEM,
+/// ready for `PgConnectOptions::ssl_root_cert_from_pem`.
+pub async fn pinned_server_cert_pem(
+    host: &str,
+    port: u16,
+    ssl_mode: PgSslMode,
+    expected: [u8; 32],
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let der = server_cert_der(host, port, ssl_mode).await?;
+    let actual: [u8; 32] = Sha256::digest(&der).into();
+    if actual != expected {
+        return Err(Box::new(CertFingerprintMismatch { expected, actual }));
+    }
+    Ok(der_to_pem(&der))
+}
+
+// PEM armor with the 64-column base64 lines OpenSSL expects
+fn der_to_pem(der: &[u8]) -> Vec<u8> {
+    let encoded = STANDARD.encode(der);
+    let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem.into_bytes()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const TEST_CERT: &[u8] = include_bytes!("../testdata/pin_server.crt");
+    const TEST_KEY: &[u8] = include_bytes!("../testdata/pin_server.key");
+
+    /// SHA-256 of the self-signed test certificate.
+    pub(crate) fn test_cert_sha256() -> [u8; 32] {
+        let der = native_tls::Certificate::from_pem(TEST_CERT).unwrap().to_der().unwrap();
+        Sha256::digest(der).into()
+    }
+
+    /// Accepts one connection, answers the SSLRequest and completes a TLS handshake
+    /// with the test certificate. Returns the port it listens on.
+    pub(crate) async fn spawn_fake_tls_postgres() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let identity = native_tls::Identity::from_pkcs8(TEST_CERT, TEST_KEY).unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 8];
+            stream.read_exact(&mut request).await.unwrap();
+            stream.write_all(b"S").await.unwrap();
+            let _ = acceptor.accept(stream).await; // Client may hang up after the handshake
+        });
+        port
+    }
+
+    #[test]
+    fn test_parse_cert_fingerprint_formats() {
+        // openssl's colon-separated output and bare hex both parse; wrong lengths don't.
+        let colon = format!("sha256 Fingerprint={}", ["AB"; 32].join(":"));
+        assert_eq!(parse_cert_fingerprint(&colon), Some([0xab; 32]));
+        assert_eq!(parse_cert_fingerprint(&"ab".repeat(32)), Some([0xab; 32]));
+        assert_eq!(parse_cert_fingerprint("ab:cd"), None);
+    }
+
+    #[tokio::test]
+    async fn test_probe_reads_server_certificate() {
+        // The probe hashes exactly the certificate the server presents.
+        let port = spawn_fake_tls_postgres().await;
+        let actual = server_cert_sha256("127.0.0.1", port, PgSslMode::Require).await.unwrap();
+        assert_eq!(actual, test_cert_sha256());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_cert_returned_as_pem() {
+        // A matching probe hands back the same certificate, re-armored as PEM.
+        let port = spawn_fake_tls_postgres().await;
+        let pem = pinned_server_cert_pem("127.0.0.1", port, PgSslMode::Require, test_cert_sha256()).await.unwrap();
+        let der = native_tls::Certificate::from_pem(&pem).unwrap().to_der().unwrap();
+        assert_eq!(<[u8; 32]>::from(Sha256::digest(der)), test_cert_sha256());
+    }
+}
@@ -0,0 +1,96 @@
+//! Demo flows shared by the binary and the tests.
+//!
+//! Everything is written to a caller-supplied sink instead of stdout, so the
+//! ✅/❌ outcome of each case can be asserted programmatically.
+
+use std::error::Error;
+use std::io::Write;
+
+use diesel::pg::PgConnection;
+
+use crate::{analyze_query, create_user_diesel, create_user_sqlx};
+
+/// Username/email pairs exercised by the demo: one safe user, then three attacks.
+pub const DEMO_TEST_CASES: [(&str, &str); 4] = [
+    ("safe_user", "safe@example.com"),
+    ("' OR 1=1;--", "malicious@example.com"),
+    ("UNION SELECT * FROM users", "union_attack@example.com"),
+    ("; DROP TABLE users", "drop_attack@example.com"),
+];
+
+pub use crate::migrations::CREATE_USER_FUNCTION_SQL;
+
+/// Runs every demo case through SQLx and Diesel, then the query monitor.
+/// Why: `main` passes stdout; tests pass a `Vec<u8>` and inspect the lines.
+pub async fn run_demo<W: Write>(
+    out: &mut W,
+    pool: &sqlx::PgPool,
+    conn: &mut PgConnection,
+) -> Result<(), Box<dyn Error>> {
+    // Test SQLx user creation
+    writeln!(out, "\n=== Testing SQLx User Creation ===")?;
+    for (username, email) in DEMO_TEST_CASES {
+        writeln!(out, "Attempting to create user: {} <{}>", username, email)?;
+        match create_user_sqlx(pool, username, email).await {
+            Ok(user) => writeln!(out, "✅ User created: {:?}", user)?,
+            Err(e) => writeln!(out, "❌ Failed: {}", e)?,
+        }
+    }
+
+    // Test Diesel ORM
+    writeln!(out, "\n=== Testing Diesel ORM ===")?;
+    for (username, email) in DEMO_TEST_CASES {
+        writeln!(out, "Attempting Diesel ORM: {} <{}>", username, email)?;
+        match create_user_diesel(conn, username, email) {
+            Ok(user) => writeln!(out, "✅ User created: {:?}", user)?,
+            Err(e) => writeln!(out, "❌ Failed: {}", e)?,
+        }
+    }
+
+    // Test query monitoring
+    writeln!(out, "\n=== Testing Query Monitoring ===")?;
+    for query in ["SELECT * FROM users", "SELECT * FROM users WHERE id=1; UNION SELECT * FROM secrets"] {
+        writeln!(out, "{} -> {:?}", query, analyze_query(query).patterns)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::Connection;
+    use sqlx::postgres::PgPoolOptions;
+
+    #[tokio::test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    async fn test_run_demo_output_pattern() {
+        // SQLx creates only the safe user; Diesel then hits the unique username
+        // and stores the attack strings as inert literals.
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPoolOptions::new().connect(&db_url).await.unwrap();
+        crate::run_migrations(&pool).await.unwrap();
+        let names: Vec<&str> = DEMO_TEST_CASES.iter().map(|(name, _)| *name).collect();
+        sqlx::query("DELETE FROM users WHERE username = ANY($1)")
+            .bind(&names)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut conn = PgConnection::establish(&db_url).unwrap();
+        conn.begin_test_transaction().unwrap(); // Diesel rows vanish with the connection
+        let mut out = Vec::new();
+        run_demo(&mut out, &pool, &mut conn).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE username = 'safe_user'").execute(&pool).await.unwrap();
+
+        let output = String::from_utf8(out).unwrap();
+        let marks: String = output
+            .lines()
+            .filter_map(|line| line.chars().next())
+            .filter(|c| matches!(c, '✅' | '❌'))
+            .collect();
+        assert_eq!(marks, "✅❌❌❌❌✅✅✅", "{output}");
+        assert!(output.contains("Attempting to create user: ' OR 1=1;-- <malicious@example.com>"));
+        assert!(output.contains("=== Testing Query Monitoring ==="));
+    }
+}
@@ -0,0 +1,207 @@
+//! Field-level validation for the user-creation endpoint, reported as JSON.
+//!
+//! `validate_user_form` checks every field and collects all failures, so a
+//! front-end can mark each bad field at once. Handlers turn that map into a
+//! `ValidationErrorResponse`: a `[{ "field", "code", "message" }]` body that
+//! names what failed without ever echoing the submitted value.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use actix_web::{error, http::{header, StatusCode}, web, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+
+use crate::store::{UserStore, UserStoreError};
+use crate::validator::{canonicalize_email, SqliValidator, ValidationError};
+use crate::DEFAULT_VALIDATOR;
+
+/// Request body for user creation.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserForm {
+    pub username: String,
+    pub email: String,
+}
+
+/// A `UserForm` whose fields all passed, with the email in canonical form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidUserForm {
+    pub username: String,
+    pub email: String,
+}
+
+/// Failures keyed by field name; sorted so responses are deterministic.
+pub type FieldErrors = BTreeMap<&'static str, ValidationError>;
+
+/// Validates every field of `form`, collecting one error per failing field.
+pub fn validate_user_form(validator: &SqliValidator, form: &UserForm) -> Result<ValidUserForm, FieldErrors> {
+    let mut errors = FieldErrors::new();
+    if let Err(e) = validator.validate(&form.username) {
+        errors.insert("username", e);
+    }
+    let email = canonicalize_email(&form.email).map_err(|e| errors.insert("email", e)).ok();
+
+    match email {
+        Some(email) if errors.is_empty() => Ok(ValidUserForm { username: form.username.clone(), email }),
+        _ => Err(errors),
+    }
+}
+
+/// One entry of a `ValidationErrorResponse`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    /// Stable code from `ValidationError::code`, for front-end logic and i18n.
+    pub code: &'static str,
+    /// Human-readable reason; never contains the submitted value.
+    pub message: String,
+}
+
+/// JSON error body for rejected requests, serialized as a bare array of `FieldError`.
+/// Implements `ResponseError`, so handlers can return it with `?`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ValidationErrorResponse {
+    errors: Vec<FieldError>,
+    #[serde(skip)]
+    status: StatusCode,
+}
+
+impl ValidationErrorResponse {
+    /// A 400 response carrying `errors`.
+    pub fn new(errors: Vec<FieldError>) -> Self {
+        Self { errors, status: StatusCode::BAD_REQUEST }
+    }
+
+    /// Overrides the status, e.g. 409 for a duplicate; must be a 4xx.
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        debug_assert!(status.is_client_error(), "validation errors are client errors");
+        self.status = status;
+        self
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+}
+
+impl From<FieldErrors> for ValidationErrorResponse {
+    fn from(errors: FieldErrors) -> Self {
+        Self::new(
+            errors
+                .into_iter()
+                .map(|(field, e)| FieldError { field, code: e.code(), message: e.to_string() })
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for ValidationErrorResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} invalid field(s)", self.errors.len())
+    }
+}
+
+impl ResponseError for ValidationErrorResponse {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(self)
+    }
+}
+
+/// `JsonConfig` that reports malformed or oversized bodies as a `ValidationErrorResponse`
+/// instead of actix's plain-text error, which can quote the offending input.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| {
+        let response = ValidationErrorResponse::new(vec![FieldError {
+            field: "body",
+            code: "malformed",
+            message: "Request body is not a valid user form".to_string(),
+        }]);
+        tracing::debug!(target: "sqli", "Rejected request body: {}", err);
+        error::InternalError::from_response(err, response.error_response()).into()
+    })
+}
+
+/// `POST` handler creating a user in `S`.
+/// Register with `web::post().to(create_user_handler::<SqlxUserStore>)` and `app_data(json_config())`.
+/// Answers 201 with the new id, 400 with per-field errors, 409 if the user exists,
+/// or 503 while the store's circuit breaker is open.
+pub async fn create_user_handler<S: UserStore + 'static>(
+    store: web::Data<S>,
+    form: web::Json<UserForm>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = validate_user_form(&DEFAULT_VALIDATOR, &form).map_err(ValidationErrorResponse::from)?;
+    match store.create(&form.username, &form.email).await {
+        Ok(user) => Ok(HttpResponse::Created().json(serde_json::json!({ "id": user.id }))),
+        Err(UserStoreError::Invalid(e)) => Err(ValidationErrorResponse::from(FieldErrors::from([("user", e)])).into()),
+        Err(UserStoreError::Duplicate) => Err(ValidationErrorResponse::new(vec![FieldError {
+            field: "user",
+            code: "duplicate",
+            message: UserStoreError::Duplicate.to_string(),
+        }])
+        .with_status(StatusCode::CONFLICT)
+        .into()),
+        Err(UserStoreError::CircuitOpen { retry_after }) => {
+            let retry_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            Ok(HttpResponse::ServiceUnavailable()
+                .insert_header((header::RETRY_AFTER, retry_secs.to_string()))
+                .finish())
+        }
+        Err(e) => {
+            tracing::error!("User creation failed: {}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryUserStore;
+    use actix_web::{test, App};
+
+    async fn post_user(body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(InMemoryUserStore::new()))
+                .app_data(json_config())
+                .route("/users", web::post().to(create_user_handler::<InMemoryUserStore>)),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/users").set_json(body).to_request();
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        (status, test::read_body_json(resp).await)
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_fields_reported_as_json() {
+        // Both bad fields come back as entries with a 400, and neither value is echoed.
+        let (status, body) =
+            post_user(serde_json::json!({ "username": "x' OR 1=1--", "email": "not-an-email" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let entries = body.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["field"], "email");
+        assert_eq!(entries[0]["code"], "invalid_email");
+        assert_eq!(entries[1]["field"], "username");
+        assert_eq!(entries[1]["code"], "sql_pattern_detected");
+        let text = body.to_string();
+        assert!(!text.contains("OR 1=1") && !text.contains("not-an-email"));
+    }
+
+    #[actix_web::test]
+    async fn test_valid_form_created_and_malformed_body_is_json() {
+        // A good form is created; a body missing fields gets a JSON 400, not plain text.
+        let (status, body) = post_user(serde_json::json!({ "username": "alice", "email": "alice@example.com" })).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["id"], 1);
+
+        let (status, body) = post_user(serde_json::json!({ "username": "alice" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body[0]["code"], "malformed");
+    }
+}
@@ -0,0 +1,258 @@
+//! Actix-Web middleware that runs every query parameter, every field of an
+//! `application/x-www-form-urlencoded` body, and every text field of a
+//! `multipart/form-data` body through `SqliValidator`.
+//!
+//! Rejections can optionally be padded to a minimum latency so an attacker
+//! can't tell blocked inputs from allowed ones by response time.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use rand::Rng;
+
+use crate::multipart;
+use crate::validator::SqliValidator;
+
+/// Default cap on buffered form and multipart bodies, file parts included.
+pub const DEFAULT_MAX_MULTIPART_BYTES: usize = 10 * 1024 * 1024;
+
+/// Latency floor applied to rejected requests.
+#[derive(Debug, Clone, Copy)]
+pub struct TimingNormalization {
+    /// Rejections never complete faster than this.
+    pub min_latency: Duration,
+    /// Up to this much random delay is added on top, so the floor itself isn't a fingerprint.
+    pub max_jitter: Duration,
+}
+
+/// SQLi guard middleware state.
+#[derive(Clone)]
+pub struct SqliGuard {
+    validator: Arc<SqliValidator>,
+    normalize_timing: Option<TimingNormalization>,
+    max_multipart_bytes: usize,
+}
+
+impl SqliGuard {
+    pub fn new(validator: SqliValidator) -> Self {
+        Self {
+            validator: Arc::new(validator),
+            normalize_timing: None,
+            max_multipart_bytes: DEFAULT_MAX_MULTIPART_BYTES,
+        }
+    }
+
+    /// Form and multipart bodies larger than this are refused with 413 instead of being buffered.
+    pub fn max_multipart_bytes(mut self, max: usize) -> Self {
+        self.max_multipart_bytes = max;
+        self
+    }
+
+    /// Pads rejection responses to the configured latency floor.
+    pub fn normalize_timing(mut self, timing: TimingNormalization) -> Self {
+        self.normalize_timing = Some(timing);
+        self
+    }
+
+    /// Middleware body: validates query parameters, form fields and multipart text fields and answers 400 on a hit.
+    /// Form and multipart bodies are buffered, scanned, then handed on unchanged; file parts are not scanned,
+    /// while text fields are held to the validator's own length and scan-size limits.
+    /// Wrap with `from_fn(move |req, next| guard.clone().guard(req, next))`.
+    pub async fn guard(
+        self,
+        mut req: ServiceRequest,
+        next: Next<impl MessageBody + 'static>,
+    ) -> Result<ServiceResponse<BoxBody>, Error> {
+        let started = Instant::now();
+
+        // Unparseable query strings are rejected rather than skipped
+        let mut rejected = match web::Query::<Vec<(String, String)>>::from_query(req.query_string()) {
+            Ok(params) => params.iter().any(|(_, value)| self.validator.validate(value).is_err()),
+            Err(_) => true,
+        };
+
+        let content_type = req.headers().get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("");
+        let is_form = is_form_urlencoded(content_type);
+        if !rejected && (is_form || multipart::is_multipart(content_type)) {
+            let boundary = multipart::boundary(content_type).map(str::to_string);
+            let payload = req.extract::<web::Payload>().await?;
+            let body = match payload.to_bytes_limited(self.max_multipart_bytes).await {
+                Ok(body) => body?,
+                Err(_) => {
+                    let response = HttpResponse::PayloadTooLarge().finish().map_into_boxed_body();
+                    return Ok(req.into_response(response));
+                }
+            };
+            rejected = if is_form {
+                // Parsed the way `web::Form` will read it; a body it can't parse is rejected too
+                let form = std::str::from_utf8(&body)
+                    .ok()
+                    .and_then(|body| web::Query::<Vec<(String, String)>>::from_query(body).ok());
+                form.is_none_or(|fields| fields.iter().any(|(_, value)| self.validator.validate(value).is_err()))
+            } else {
+                match boundary.map(|b| multipart::text_fields(&body, &b)) {
+                    Some(Ok(fields)) => fields.iter().any(|(_, value)| self.validator.validate(value).is_err()),
+                    _ => true, // No boundary or broken framing: don't guess what the handler would read
+                }
+            };
+            req.set_payload(Payload::from(body));
+        }
+
+        if !rejected {
+            return next.call(req).await.map(|res| res.map_into_boxed_body());
+        }
+
+        if let Some(timing) = self.normalize_timing {
+            let jitter = if timing.max_jitter.is_zero() {
+                Duration::ZERO
+            } else {
+                rand::thread_rng().gen_range(Duration::ZERO..=timing.max_jitter)
+            };
+            let floor = timing.min_latency + jitter;
+            let elapsed = started.elapsed();
+            if elapsed < floor {
+                actix_web::rt::time::sleep(floor - elapsed).await;
+            }
+        }
+
+        // Never echo the input back: the reason stays server-side
+        let response = HttpResponse::BadRequest()
+            .body("Invalid input")
+            .map_into_boxed_body();
+        Ok(req.into_response(response))
+    }
+}
+
+/// True for `application/x-www-form-urlencoded` content types, whatever their case or parameters.
+pub(crate) fn is_form_urlencoded(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/x-www-form-urlencoded"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, middleware::from_fn, test, App};
+
+    #[actix_web::test]
+    async fn test_clean_query_passes() {
+        // Benign parameters reach the handler untouched.
+        let guard = SqliGuard::new(SqliValidator::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| guard.clone().guard(req, next)))
+                .route("/users", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/users?name=alice").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_rejection_meets_latency_floor() {
+        // A blocked request takes at least the configured minimum latency.
+        let floor = Duration::from_millis(150);
+        let guard = SqliGuard::new(SqliValidator::default()).normalize_timing(TimingNormalization {
+            min_latency: floor,
+            max_jitter: Duration::from_millis(20),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| guard.clone().guard(req, next)))
+                .route("/users", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let started = Instant::now();
+        let req = test::TestRequest::get().uri("/users?name=%27%20OR%201%3D1--").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(started.elapsed() >= floor);
+    }
+
+    #[actix_web::test]
+    async fn test_form_post_fields_scanned() {
+        // A urlencoded login form is checked like query parameters, whatever the content type's case.
+        let guard = SqliGuard::new(SqliValidator::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| guard.clone().guard(req, next)))
+                .route("/login", web::post().to(|form: web::Form<Vec<(String, String)>>| async move {
+                    form.into_inner().len().to_string()
+                })),
+        )
+        .await;
+        let post = |content_type: &'static str, body: &'static str| {
+            test::TestRequest::post()
+                .uri("/login")
+                .insert_header((header::CONTENT_TYPE, content_type))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let form = "application/x-www-form-urlencoded";
+        let resp = test::call_service(&app, post(form, "user=admin%27+OR+%271%27%3D%271&pass=x")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = test::call_service(&app, post("Application/X-WWW-Form-Urlencoded", "user=x%27--")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = test::call_service(&app, post(form, "user=alice&pass=hunter2")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(test::read_body(resp).await, "2");
+    }
+
+    #[actix_web::test]
+    async fn test_multipart_text_fields_scanned() {
+        // A malicious caption is rejected; a clean one passes with the body intact next to a
+        // binary file part, and text files are uploaded unscanned.
+        let guard = SqliGuard::new(SqliValidator::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| guard.clone().guard(req, next)))
+                .route("/upload", web::post().to(|body: web::Bytes| async move { body })),
+        )
+        .await;
+        let upload = |caption: &str| {
+            let mut body = format!("--XyZ\r\nContent-Disposition: form-data; name=\"caption\"\r\n\r\n{caption}\r\n")
+                .into_bytes();
+            body.extend_from_slice(
+                b"--XyZ\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"a.jpg\"\r\n\
+                  Content-Type: image/jpeg\r\n\r\n\xff\xd8DROP TABLE users;\r\n--XyZ--\r\n",
+            );
+            test::TestRequest::post()
+                .uri("/upload")
+                .insert_header((header::CONTENT_TYPE, "multipart/form-data; boundary=XyZ"))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, upload("nice'; DROP TABLE users; --")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // A clean text file over the validator's 100-character limit, with apostrophes, is uploaded as-is
+        let csv = "id,name\r\n".to_string() + &"1,O'Brien selected\r\n".repeat(10);
+        let text_upload = format!(
+            "--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"people.csv\"\r\n\
+             Content-Type: text/csv\r\n\r\n{csv}\r\n--XyZ--\r\n"
+        );
+        let req = test::TestRequest::post()
+            .uri("/upload")
+            .insert_header((header::CONTENT_TYPE, "multipart/form-data; boundary=XyZ"))
+            .set_payload(text_upload)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let resp = test::call_service(&app, upload("Holiday photos")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let echoed = test::read_body(resp).await;
+        assert!(echoed.starts_with(b"--XyZ\r\n") && echoed.ends_with(b"--XyZ--\r\n"));
+    }
+}
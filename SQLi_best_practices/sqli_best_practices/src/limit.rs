@@ -0,0 +1,107 @@
+//! Actix-Web middleware that caps how many requests are in flight at once.
+//!
+//! Without a cap, a flood of slow requests queues on the connection pool
+//! (`max_connections(10)`) and piles up in memory. Excess requests are shed
+//! immediately with `503 Service Unavailable` and a `Retry-After` hint.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    Error, HttpResponse,
+};
+use tokio::sync::Semaphore;
+
+/// Default `Retry-After` sent with a 503.
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Concurrency limit middleware state; clones share the same permits.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+    permits: Arc<Semaphore>,
+    retry_after: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// Allows at most `max_in_flight` requests at a time.
+    /// Size it near the pool's `max_connections` so waiting happens here, cheaply,
+    /// instead of inside the pool's acquire timeout.
+    pub fn new(max_in_flight: usize) -> Self {
+        Self { permits: Arc::new(Semaphore::new(max_in_flight)), retry_after: DEFAULT_RETRY_AFTER }
+    }
+
+    /// Sets the `Retry-After` hint (whole seconds, rounded up, at least 1).
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Permits not currently held by a request.
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    /// Middleware body: holds a permit for the whole request, or answers 503 if none is free.
+    /// Wrap with `from_fn(move |req, next| limit.clone().guard(req, next))`.
+    pub async fn guard(
+        self,
+        req: ServiceRequest,
+        next: Next<impl MessageBody + 'static>,
+    ) -> Result<ServiceResponse<BoxBody>, Error> {
+        // Shed instead of queueing: waiting callers would hold memory and sockets anyway
+        let Ok(_permit) = self.permits.clone().try_acquire_owned() else {
+            tracing::warn!(path = req.path(), "Concurrency limit reached, shedding request");
+            let retry_secs = self.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            let response = HttpResponse::ServiceUnavailable()
+                .insert_header((header::RETRY_AFTER, retry_secs.to_string()))
+                .body("Server busy")
+                .map_into_boxed_body();
+            return Ok(req.into_response(response));
+        };
+        next.call(req).await.map(|res| res.map_into_boxed_body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, middleware::from_fn, test, web, App};
+
+    #[actix_web::test]
+    async fn test_request_over_limit_gets_503() {
+        // With two slow requests in flight, a third is shed; once they finish, capacity returns.
+        let limit = ConcurrencyLimit::new(2).retry_after(Duration::from_secs(5));
+        let probe = limit.clone();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| limit.clone().guard(req, next)))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+                        "done"
+                    }),
+                ),
+        )
+        .await;
+
+        let call = |delay_ms: u64| {
+            let app = &app;
+            async move {
+                actix_web::rt::time::sleep(Duration::from_millis(delay_ms)).await;
+                test::call_service(app, test::TestRequest::get().uri("/slow").to_request()).await
+            }
+        };
+        let (first, second, third) = tokio::join!(call(0), call(0), call(50));
+
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(third.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(third.headers().get(header::RETRY_AFTER).unwrap(), "5");
+        assert_eq!(probe.available(), 2);
+    }
+}
@@ -0,0 +1,151 @@
+//! Counters for validation and query monitoring, for dashboards and alerting.
+//!
+//! `SecurityMetrics` is a set of atomics that the `*_with_metrics` variants of
+//! `validate_input` and `analyze_query` bump; share one per process (a
+//! `static` or an `Arc`). `snapshot` copies the counters into a plain
+//! `MetricsSnapshot`, which `to_prometheus` renders in the text exposition format.
+
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use crate::{QueryThreatReport, ThreatPattern, ValidationError};
+
+/// Live counters; every method takes `&self`, so one instance can be shared freely.
+#[derive(Debug, Default)]
+pub struct SecurityMetrics {
+    inputs_validated: AtomicU64,
+    inputs_blocked: AtomicU64,
+    queries_analyzed: AtomicU64,
+    queries_flagged: AtomicU64,
+    union_detected: AtomicU64,
+    stacked_query_detected: AtomicU64,
+    comment_detected: AtomicU64,
+    tautology_detected: AtomicU64,
+    time_based_detected: AtomicU64,
+}
+
+/// Point-in-time copy of `SecurityMetrics`; all counters are totals since start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MetricsSnapshot {
+    pub inputs_validated: u64,
+    /// Inputs `validate_input` rejected, for any reason.
+    pub inputs_blocked: u64,
+    pub queries_analyzed: u64,
+    /// Queries with at least one `ThreatPattern`.
+    pub queries_flagged: u64,
+    pub union_detected: u64,
+    pub stacked_query_detected: u64,
+    pub comment_detected: u64,
+    pub tautology_detected: u64,
+    pub time_based_detected: u64,
+}
+
+impl SecurityMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts one validation and, if it failed, one blocked input.
+    pub fn record_validation(&self, result: &Result<(), ValidationError>) {
+        self.inputs_validated.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.inputs_blocked.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts one analyzed query, whether it was flagged, and each pattern it carried.
+    pub fn record_analysis(&self, report: &QueryThreatReport) {
+        self.queries_analyzed.fetch_add(1, Ordering::Relaxed);
+        if !report.is_clean() {
+            self.queries_flagged.fetch_add(1, Ordering::Relaxed);
+        }
+        for pattern in &report.patterns {
+            let counter = match pattern {
+                ThreatPattern::UnionBased => &self.union_detected,
+                ThreatPattern::StackedQuery => &self.stacked_query_detected,
+                ThreatPattern::CommentInjection => &self.comment_detected,
+                ThreatPattern::Tautology => &self.tautology_detected,
+                ThreatPattern::TimeBased => &self.time_based_detected,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Current values. Counters are read one by one, so a snapshot taken mid-update
+    /// can be off by the in-flight call; fine for monitoring, not for accounting.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let read = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        MetricsSnapshot {
+            inputs_validated: read(&self.inputs_validated),
+            inputs_blocked: read(&self.inputs_blocked),
+            queries_analyzed: read(&self.queries_analyzed),
+            queries_flagged: read(&self.queries_flagged),
+            union_detected: read(&self.union_detected),
+            stacked_query_detected: read(&self.stacked_query_detected),
+            comment_detected: read(&self.comment_detected),
+            tautology_detected: read(&self.tautology_detected),
+            time_based_detected: read(&self.time_based_detected),
+        }
+    }
+}
+
+impl MetricsSnapshot {
+    /// Prometheus text exposition: one `sqli_*_total` counter per field, patterns as a labelled family.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in [
+            ("sqli_inputs_validated_total", self.inputs_validated),
+            ("sqli_inputs_blocked_total", self.inputs_blocked),
+            ("sqli_queries_analyzed_total", self.queries_analyzed),
+            ("sqli_queries_flagged_total", self.queries_flagged),
+        ] {
+            let _ = writeln!(out, "# TYPE {name} counter\n{name} {value}");
+        }
+        out.push_str("# TYPE sqli_patterns_detected_total counter\n");
+        for (pattern, value) in [
+            ("union", self.union_detected),
+            ("stacked_query", self.stacked_query_detected),
+            ("comment", self.comment_detected),
+            ("tautology", self.tautology_detected),
+            ("time_based", self.time_based_detected),
+        ] {
+            let _ = writeln!(out, "sqli_patterns_detected_total{{pattern=\"{pattern}\"}} {value}");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyze_query_with_metrics, validate_input_with_metrics};
+
+    #[test]
+    fn test_counters_follow_allowed_and_blocked_paths() {
+        // Allowed inputs only bump `validated`; blocked ones bump both; each pattern has its own counter.
+        let metrics = SecurityMetrics::new();
+        assert!(validate_input_with_metrics("alice", &metrics).is_ok());
+        assert!(validate_input_with_metrics("' OR 1=1--", &metrics).is_err());
+        analyze_query_with_metrics("SELECT id FROM users", &metrics);
+        analyze_query_with_metrics("SELECT 1 UNION SELECT password FROM users; DROP TABLE users", &metrics);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(
+            snapshot,
+            MetricsSnapshot {
+                inputs_validated: 2,
+                inputs_blocked: 1,
+                queries_analyzed: 2,
+                queries_flagged: 1,
+                union_detected: 1,
+                stacked_query_detected: 1,
+                ..Default::default()
+            }
+        );
+        let exported = snapshot.to_prometheus();
+        assert!(exported.contains("sqli_inputs_blocked_total 1\n"));
+        assert!(exported.contains("sqli_patterns_detected_total{pattern=\"union\"} 1\n"));
+    }
+}
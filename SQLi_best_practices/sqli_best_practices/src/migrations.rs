@@ -0,0 +1,197 @@
+//! Schema for the `users` table and the `create_user` procedure, in one place.
+//! The SQL itself lives in `migrations/`, so `diesel migration run` and
+//! `run_migrations` never drift apart.
+//!
+//! Every statement is idempotent (`IF NOT EXISTS`, `CREATE OR REPLACE`), so
+//! `run_migrations` can run on every startup and from every test. Apps that
+//! leave schema changes to a deploy step can call `ensure_procedure_exists`
+//! at startup instead, to fail fast with a clear error.
+
+use std::fmt;
+
+use sqlx::PgPool;
+
+/// The `users` table, matching `schema.rs`; read from the Diesel migrations directory
+/// so the CLI and `run_migrations` apply the same SQL.
+pub const CREATE_USERS_TABLE_SQL: &str =
+    include_str!("../migrations/2026-10-16-000000_users_table_if_not_exists/up.sql");
+
+/// Stored procedure called by `create_user_sqlx`; expects the `users` table to exist.
+pub const CREATE_USER_FUNCTION_SQL: &str =
+    include_str!("../migrations/2026-10-16-000001_replace_create_user_function/up.sql");
+
+/// Argument types of `create_user`, spelled the way `format_type` (and so `ensure_procedure_exists`) prints them.
+pub const CREATE_USER_ARG_TYPES: [&str; 2] = ["character varying", "character varying"];
+
+/// Named steps in the order `run_migrations` applies them.
+pub const MIGRATIONS: [(&str, &str); 2] = [
+    ("create_users_table", CREATE_USERS_TABLE_SQL),
+    ("create_user_function", CREATE_USER_FUNCTION_SQL),
+];
+
+// Arbitrary constant shared by every process running these migrations
+const MIGRATION_LOCK_KEY: i64 = 0x5351_4c49_6d69_6772; // "SQLImigr"
+
+/// Applies `MIGRATIONS` in one transaction; safe to call repeatedly.
+/// Why: A transaction-scoped advisory lock serializes concurrent callers (parallel
+/// tests, several app instances booting), which `IF NOT EXISTS` alone does not.
+pub async fn run_migrations(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)").bind(MIGRATION_LOCK_KEY).execute(&mut *tx).await?;
+    for (name, sql) in MIGRATIONS {
+        tracing::debug!(migration = name, "Applying migration");
+        sqlx::query(sql).execute(&mut *tx).await?;
+    }
+    tx.commit().await
+}
+
+/// Carried by `ProcedureCheckError::Missing` when no visible function has the expected signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingProcedure {
+    pub name: String,
+    pub arg_types: Vec<String>,
+    /// Argument lists of the functions by that name that do exist (other overloads).
+    pub found: Vec<String>,
+}
+
+impl fmt::Display for MissingProcedure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Missing procedure {}({})", self.name, self.arg_types.join(", "))?;
+        if self.found.is_empty() {
+            write!(f, "; no function by that name exists, have the migrations run?")
+        } else {
+            write!(f, "; found only {}({})", self.name, self.found.join(&format!("), {}(", self.name)))
+        }
+    }
+}
+
+impl std::error::Error for MissingProcedure {}
+
+/// Error from `ensure_procedure_exists`: the catalog query failed, or it found no match.
+#[derive(Debug)]
+pub enum ProcedureCheckError {
+    Sqlx(sqlx::Error),
+    Missing(MissingProcedure),
+}
+
+impl fmt::Display for ProcedureCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sqlx(e) => write!(f, "{}", e),
+            Self::Missing(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProcedureCheckError {}
+
+impl From<sqlx::Error> for ProcedureCheckError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Sqlx(e)
+    }
+}
+
+/// Checks that function `name` exists with exactly `arg_types` (as `format_type` prints them,
+/// e.g. "character varying", "integer"). Call it at startup, before serving traffic.
+/// Why: Without it, a skipped migration surfaces as a cryptic "function does not exist" on the first request.
+pub async fn ensure_procedure_exists(
+    pool: &PgPool,
+    name: &str,
+    arg_types: &[&str],
+) -> Result<(), ProcedureCheckError> {
+    let found: Vec<String> = sqlx::query_scalar(
+        "SELECT oidvectortypes(proargtypes) FROM pg_proc
+         WHERE proname = $1 AND pg_function_is_visible(oid) ORDER BY 1",
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    if found.contains(&arg_types.join(", ")) {
+        return Ok(());
+    }
+    Err(ProcedureCheckError::Missing(MissingProcedure {
+        name: name.to_string(),
+        arg_types: arg_types.iter().map(|t| t.to_string()).collect(),
+        found,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::with_temp_database;
+
+    #[tokio::test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    async fn test_run_migrations_twice_is_noop() {
+        // A second run changes nothing: same columns, one create_user, and data survives.
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let admin = PgPool::connect(&db_url).await.unwrap();
+        with_temp_database(&admin, "migrations_test", |pool, _| async move {
+            run_migrations(&pool).await.unwrap();
+            sqlx::query("SELECT * FROM create_user('mig_alice', 'mig_alice@example.com')")
+                .execute(&pool)
+                .await
+                .unwrap();
+            run_migrations(&pool).await.unwrap();
+
+            let columns: Vec<(String, String)> = sqlx::query_as(
+                "SELECT column_name::text, data_type::text FROM information_schema.columns
+                 WHERE table_name = 'users' ORDER BY ordinal_position",
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+            assert_eq!(
+                columns,
+                [
+                    ("id".to_string(), "integer".to_string()),
+                    ("username".to_string(), "character varying".to_string()),
+                    ("email".to_string(), "character varying".to_string()),
+                ]
+            );
+            let functions: i64 = sqlx::query_scalar("SELECT count(*) FROM pg_proc WHERE proname = 'create_user'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(functions, 1);
+            let users: i64 = sqlx::query_scalar("SELECT count(*) FROM users").fetch_one(&pool).await.unwrap();
+            assert_eq!(users, 1);
+        })
+        .await
+        .unwrap();
+    }
+
+    async fn missing_procedure(pool: &PgPool, arg_types: &[&str]) -> MissingProcedure {
+        match ensure_procedure_exists(pool, "create_user", arg_types).await {
+            Err(ProcedureCheckError::Missing(missing)) => missing,
+            other => panic!("expected a MissingProcedure error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    async fn test_ensure_procedure_exists() {
+        // Before migrations the check names what's missing; afterwards it passes, and a wrong signature fails.
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let admin = PgPool::connect(&db_url).await.unwrap();
+        with_temp_database(&admin, "procedure_check", |pool, _| async move {
+            let missing = missing_procedure(&pool, &CREATE_USER_ARG_TYPES).await;
+            assert!(missing.found.is_empty());
+            assert_eq!(
+                missing.to_string(),
+                "Missing procedure create_user(character varying, character varying); \
+                 no function by that name exists, have the migrations run?"
+            );
+
+            run_migrations(&pool).await.unwrap();
+            ensure_procedure_exists(&pool, "create_user", &CREATE_USER_ARG_TYPES).await.unwrap();
+
+            let missing = missing_procedure(&pool, &["integer"]).await;
+            assert_eq!(missing.found, ["character varying, character varying"]);
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! Per-source detection of SQLi probing.
+//!
+//! One flagged query may be a typo; a burst from the same address is somebody
+//! working through a payload list. `QueryMonitor` keeps a sliding window of
+//! flagged-query timestamps per source IP (flagged meaning `analyze_query`
+//! found at least one pattern) and reports a source as suspicious once the
+//! count in the window reaches the threshold, e.g. to block it at the edge.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{analyze_query, QueryThreatReport};
+
+/// Flagged queries within `DEFAULT_PROBE_WINDOW` that make a source suspicious.
+pub const DEFAULT_PROBE_THRESHOLD: usize = 5;
+/// Length of the sliding window.
+pub const DEFAULT_PROBE_WINDOW: Duration = Duration::from_secs(60);
+
+// Past this many tracked sources, `record` sweeps out the ones with no hits left in the window
+const SWEEP_AT_SOURCES: usize = 10_000;
+
+/// Monotonic time source, injectable so tests can move time instead of sleeping.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// `Instant::now()`; the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Sliding-window counter of flagged queries per source; share one per app (e.g. in an `Arc`).
+#[derive(Debug)]
+pub struct QueryMonitor<C: Clock = SystemClock> {
+    flagged: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+    threshold: usize,
+    window: Duration,
+    clock: C,
+}
+
+impl QueryMonitor {
+    /// Suspicious after `DEFAULT_PROBE_THRESHOLD` flagged queries in `DEFAULT_PROBE_WINDOW`.
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl Default for QueryMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> QueryMonitor<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            flagged: Mutex::new(HashMap::new()),
+            threshold: DEFAULT_PROBE_THRESHOLD,
+            window: DEFAULT_PROBE_WINDOW,
+            clock,
+        }
+    }
+
+    /// Suspicious after `flagged` flagged queries (at least 1) within `window`.
+    pub fn threshold(mut self, flagged: usize, window: Duration) -> Self {
+        self.threshold = flagged.max(1);
+        self.window = window;
+        self
+    }
+
+    /// Analyzes `query` and, if anything was flagged, counts it against `source_ip`.
+    /// Returns the report so callers can act on the query itself too.
+    pub fn record(&self, source_ip: IpAddr, query: &str) -> QueryThreatReport {
+        let report = analyze_query(query);
+        if report.is_clean() {
+            return report;
+        }
+
+        let now = self.clock.now();
+        let mut flagged = self.flagged.lock().unwrap();
+        if flagged.len() >= SWEEP_AT_SOURCES {
+            flagged.retain(|_, hits| {
+                self.prune(hits, now);
+                !hits.is_empty()
+            });
+        }
+        let hits = flagged.entry(source_ip).or_default();
+        self.prune(hits, now);
+        hits.push_back(now);
+        if hits.len() == self.threshold {
+            tracing::warn!(
+                target: "sqli",
+                source = %source_ip,
+                window_secs = self.window.as_secs(),
+                "Source crossed the SQLi probing threshold"
+            );
+        }
+        report
+    }
+
+    /// True once `source_ip` has at least the threshold of flagged queries in the current window.
+    pub fn is_suspicious(&self, source_ip: IpAddr) -> bool {
+        let now = self.clock.now();
+        let mut flagged = self.flagged.lock().unwrap();
+        let Some(hits) = flagged.get_mut(&source_ip) else {
+            return false;
+        };
+        self.prune(hits, now);
+        if hits.is_empty() {
+            flagged.remove(&source_ip);
+            return false;
+        }
+        hits.len() >= self.threshold
+    }
+
+    // Drops timestamps that have slid out of the window
+    fn prune(&self, hits: &mut VecDeque<Instant>, now: Instant) {
+        while hits.front().is_some_and(|&at| now.duration_since(at) >= self.window) {
+            hits.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone)]
+    struct MockClock(Arc<Mutex<Instant>>);
+
+    impl MockClock {
+        fn new() -> Self {
+            Self(Arc::new(Mutex::new(Instant::now())))
+        }
+
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    const PROBE: &str = "SELECT * FROM users WHERE id = 1 OR 1=1";
+
+    #[test]
+    fn test_threshold_crossed_within_window() {
+        // Five flagged queries make a source suspicious; clean queries and other sources don't count.
+        let monitor = QueryMonitor::with_clock(MockClock::new());
+        let (attacker, bystander): (IpAddr, IpAddr) = ("203.0.113.7".parse().unwrap(), "198.51.100.2".parse().unwrap());
+        for _ in 0..4 {
+            assert!(!monitor.record(attacker, PROBE).is_clean());
+            monitor.record(attacker, "SELECT id FROM users WHERE id = $1");
+        }
+        assert!(!monitor.is_suspicious(attacker));
+        monitor.record(attacker, PROBE);
+        assert!(monitor.is_suspicious(attacker));
+        assert!(!monitor.is_suspicious(bystander));
+    }
+
+    #[test]
+    fn test_hits_expire_as_window_slides() {
+        // Hits spaced 20s apart never reach 5 inside 60s; after a quiet minute the source is clean again.
+        let clock = MockClock::new();
+        let monitor = QueryMonitor::with_clock(clock.clone());
+        let source: IpAddr = "2001:db8::1".parse().unwrap();
+        for _ in 0..6 {
+            monitor.record(source, PROBE);
+            clock.advance(Duration::from_secs(20));
+        }
+        assert!(!monitor.is_suspicious(source));
+
+        let strict = QueryMonitor::with_clock(clock.clone()).threshold(2, Duration::from_secs(10));
+        strict.record(source, PROBE);
+        strict.record(source, PROBE);
+        assert!(strict.is_suspicious(source));
+        clock.advance(Duration::from_secs(10));
+        assert!(!strict.is_suspicious(source));
+    }
+}
@@ -0,0 +1,179 @@
+//! Minimal `multipart/form-data` reader for the SQLi middleware.
+//!
+//! Upload forms carry ordinary text fields (title, description, ...) next to
+//! the file, and those fields end up in queries just like query parameters do.
+//! `text_fields` pulls out the fields so they can be validated. File parts (those
+//! with a `filename`) are uploads, not query input, and are never scanned: a CSV or
+//! JSON file is free to contain apostrophes and SQL words. A field without a filename
+//! is scanned even under a non-text `Content-Type`, unless its content isn't UTF-8.
+
+use std::fmt;
+
+/// Why a multipart body could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultipartError {
+    /// `Content-Type` is multipart but carries no usable `boundary` parameter.
+    MissingBoundary,
+    /// The body doesn't follow the boundary framing, or a part has no headers.
+    Malformed,
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingBoundary => write!(f, "Multipart body has no boundary"),
+            Self::Malformed => write!(f, "Malformed multipart body"),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+/// True for `multipart/form-data` content types, whatever their parameters.
+pub fn is_multipart(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("multipart/form-data"))
+}
+
+/// The `boundary` parameter of a `multipart/form-data` content type, unquoted.
+pub fn boundary(content_type: &str) -> Option<&str> {
+    if !is_multipart(content_type) {
+        return None;
+    }
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        let value = value.trim().trim_matches('"');
+        (key.trim().eq_ignore_ascii_case("boundary") && !value.is_empty()).then_some(value)
+    })
+}
+
+/// Text fields of a multipart body as `(name, value)` pairs, in body order.
+/// Plain fields that aren't UTF-8 are decoded lossily, so ASCII payloads are still visible.
+/// File parts are skipped; non-text fields are returned when their content is valid UTF-8.
+pub fn text_fields(body: &[u8], boundary: &str) -> Result<Vec<(String, String)>, MultipartError> {
+    let delimiter = format!("--{boundary}");
+    let next_delimiter = format!("\r\n--{boundary}");
+    let start = find(body, delimiter.as_bytes()).ok_or(MultipartError::Malformed)?;
+    let mut rest = &body[start + delimiter.len()..];
+
+    let mut fields = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(fields); // Closing delimiter
+        }
+        rest = rest.strip_prefix(b"\r\n").ok_or(MultipartError::Malformed)?;
+        let end = find(rest, next_delimiter.as_bytes()).ok_or(MultipartError::Malformed)?;
+        if let Some(field) = text_part(&rest[..end])? {
+            fields.push(field);
+        }
+        rest = &rest[end + next_delimiter.len()..];
+    }
+}
+
+// One part: headers, a blank line, then the content. None for file parts and binary fields.
+fn text_part(part: &[u8]) -> Result<Option<(String, String)>, MultipartError> {
+    let split = find(part, b"\r\n\r\n").ok_or(MultipartError::Malformed)?;
+    let headers = std::str::from_utf8(&part[..split]).map_err(|_| MultipartError::Malformed)?;
+    let content = &part[split + 4..];
+
+    let mut name = None;
+    let mut plain_field = true;
+    for header in headers.split("\r\n") {
+        let Some((key, value)) = header.split_once(':') else { continue };
+        let key = key.trim();
+        if key.eq_ignore_ascii_case("content-type") && !value.trim().to_ascii_lowercase().starts_with("text/") {
+            plain_field = false;
+        }
+        if key.eq_ignore_ascii_case("content-disposition") {
+            for param in value.split(';').skip(1) {
+                let (param_key, param_value) = param.split_once('=').unwrap_or((param, ""));
+                match param_key.trim().to_ascii_lowercase().as_str() {
+                    "filename" | "filename*" => return Ok(None),
+                    "name" => name = Some(param_value.trim().trim_matches('"').to_string()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    let name = name.ok_or(MultipartError::Malformed)?;
+    let value = if plain_field {
+        String::from_utf8_lossy(content).into_owned()
+    } else {
+        // A field is query input whatever type it claims; only content that isn't text is left alone
+        match std::str::from_utf8(content) {
+            Ok(text) => text.to_string(),
+            Err(_) => return Ok(None),
+        }
+    };
+    Ok(Some((name, value)))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_fields_skip_binary_parts() {
+        // Text parts come back in order; binary uploads are never returned.
+        let body = b"--XyZ\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+            Holiday photos\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"photo\"; filename=\"a.jpg\"\r\n\
+            Content-Type: image/jpeg\r\n\r\n\
+            \xff\xd8\xff\xe0' OR 1=1 --\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"blob\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n\
+            \x80\x01\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"note\"\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\r\n\
+            two\r\nlines\r\n\
+            --XyZ--\r\n";
+        let fields = text_fields(body, "XyZ").unwrap();
+        assert_eq!(
+            fields,
+            [("title".to_string(), "Holiday photos".to_string()), ("note".to_string(), "two\r\nlines".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_clean_text_upload_is_not_scanned() {
+        // A long CSV with apostrophes and SQL words is a file, not input; a field claiming a binary type is.
+        let csv = format!("name,comment\r\n{}", "O'Brien,\"select the best one\"\r\n".repeat(10));
+        assert!(csv.len() > 100);
+        let body = format!(
+            "--XyZ\r\n\
+             Content-Disposition: form-data; name=\"report\"; filename=\"report.csv\"\r\n\
+             Content-Type: text/csv\r\n\r\n\
+             {csv}\r\n\
+             --XyZ\r\n\
+             Content-Disposition: form-data; name=\"comment\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             1 UNION SELECT password FROM users\r\n\
+             --XyZ--\r\n"
+        );
+        let fields = text_fields(body.as_bytes(), "XyZ").unwrap();
+        assert_eq!(fields, [("comment".to_string(), "1 UNION SELECT password FROM users".to_string())]);
+    }
+
+    #[test]
+    fn test_boundary_and_malformed_bodies() {
+        // The boundary may be quoted; truncated bodies are errors, not empty results.
+        assert_eq!(boundary("multipart/form-data; boundary=\"a b\""), Some("a b"));
+        assert_eq!(boundary("Multipart/Form-Data;boundary=XyZ"), Some("XyZ"));
+        assert_eq!(boundary("multipart/form-data"), None);
+        assert_eq!(boundary("application/json; boundary=XyZ"), None);
+
+        let truncated = b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nvalue";
+        assert_eq!(text_fields(truncated, "XyZ"), Err(MultipartError::Malformed));
+        assert_eq!(text_fields(b"no delimiter here", "XyZ"), Err(MultipartError::Malformed));
+    }
+}
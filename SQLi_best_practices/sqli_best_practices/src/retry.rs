@@ -0,0 +1,162 @@
+//! Retry with exponential backoff for transient database failures.
+//!
+//! A dropped connection or a pool that timed out during a failover says nothing
+//! about the request, so trying again shortly is safe. A constraint violation or
+//! a validation error will fail the same way every time, so those return at once:
+//! retrying a duplicate username only delays the 409.
+
+use std::error::Error;
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Longest pause between two attempts, however many attempts are configured.
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Errors that `with_retry` may retry.
+pub trait TransientError {
+    /// True if the same call could succeed when repeated unchanged.
+    fn is_transient(&self) -> bool;
+}
+
+impl TransientError for sqlx::Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::Io(_) | Self::PoolTimedOut => true,
+            // Class 08 is connection_exception; 57P03 is cannot_connect_now (server starting up)
+            Self::Database(db) => db.code().is_some_and(|code| code.starts_with("08") || code == "57P03"),
+            _ => false,
+        }
+    }
+}
+
+impl TransientError for Box<dyn Error> {
+    fn is_transient(&self) -> bool {
+        self.downcast_ref::<sqlx::Error>().is_some_and(TransientError::is_transient)
+    }
+}
+
+/// Runs `op` up to `attempts` times, sleeping between tries while the error is transient.
+/// The n-th retry waits between half and all of `base_delay * 2^(n-1)` (capped at
+/// `MAX_RETRY_DELAY`), so clients that failed together do not retry in lockstep.
+/// Why: Any other error (unique violation, invalid input) is returned from the first attempt.
+pub async fn with_retry<F, Fut, T, E>(attempts: u32, base_delay: Duration, mut op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: TransientError,
+{
+    let attempts = attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < attempts && e.is_transient() => {
+                let delay = backoff(base_delay, attempt);
+                tracing::warn!(
+                    target: "sqli",
+                    attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    "Transient database error, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Equal jitter: half the exponential step fixed, the other half random
+fn backoff(base_delay: Duration, attempt: u32) -> Duration {
+    let step = base_delay.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_RETRY_DELAY);
+    let half = step / 2;
+    half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::fmt;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    // Stand-in for a Postgres error with a given SQLSTATE
+    #[derive(Debug)]
+    struct MockDbError(&'static str);
+
+    impl fmt::Display for MockDbError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "mock database error {}", self.0)
+        }
+    }
+
+    impl std::error::Error for MockDbError {}
+
+    impl DatabaseError for MockDbError {
+        fn message(&self) -> &str {
+            "mock"
+        }
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+        fn kind(&self) -> ErrorKind {
+            if self.0 == "23505" { ErrorKind::UniqueViolation } else { ErrorKind::Other }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_retried_until_success() {
+        // Two pool timeouts, then success: three calls, one result.
+        let calls = AtomicU32::new(0);
+        let result = with_retry(5, Duration::from_millis(1), || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(sqlx::Error::PoolTimedOut),
+                1 => Err(sqlx::Error::Database(Box::new(MockDbError("08006")))),
+                _ => Ok("created"),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "created");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_unique_violation_fails_fast() {
+        // A duplicate username is returned from the first attempt, even behind Box<dyn Error>.
+        let calls = AtomicU32::new(0);
+        let result: Result<(), Box<dyn Error>> = with_retry(5, Duration::from_millis(1), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(sqlx::Error::Database(Box::new(MockDbError("23505"))).into())
+        })
+        .await;
+        let err = result.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(sqlx::Error::Database(db)) if db.is_unique_violation()));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_attempts() {
+        // A persistent outage surfaces the last error once the attempts are spent.
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = with_retry(3, Duration::from_millis(1), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(sqlx::Error::PoolTimedOut)
+        })
+        .await;
+        assert!(matches!(result, Err(sqlx::Error::PoolTimedOut)));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(backoff(Duration::from_secs(1), 30) <= MAX_RETRY_DELAY);
+    }
+}
@@ -0,0 +1,378 @@
+//! Persistence behind a trait, so validation logic can be tested without Postgres.
+//!
+//! `create_user_checked` and `find_user_checked` run the shared validator and then
+//! hand off to any `UserStore`: `SqlxUserStore`, `DieselUserStore`, or the
+//! `InMemoryUserStore` used in tests.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::future::{self, Future};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+
+use crate::breaker::{BreakerError, CircuitBreaker};
+use crate::{canonicalize_email, ValidationError, DEFAULT_VALIDATOR};
+
+/// A persisted user, independent of which backend stored it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredUser {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+}
+
+/// Errors from `UserStore` operations and the checked helpers.
+#[derive(Debug)]
+pub enum UserStoreError {
+    /// Input failed validation and never reached the store.
+    Invalid(ValidationError),
+    /// Username or email is already taken.
+    Duplicate,
+    /// Anything else the backend reported.
+    Backend(Box<dyn Error + Send + Sync>),
+    /// The store's circuit breaker is open after repeated backend failures; nothing was sent.
+    CircuitOpen { retry_after: Duration },
+}
+
+impl fmt::Display for UserStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Invalid(e) => write!(f, "{}", e),
+            Self::Duplicate => write!(f, "User already exists"),
+            Self::Backend(e) => write!(f, "Storage error: {}", e),
+            Self::CircuitOpen { .. } => write!(f, "Storage temporarily unavailable"),
+        }
+    }
+}
+
+impl Error for UserStoreError {}
+
+impl From<ValidationError> for UserStoreError {
+    fn from(e: ValidationError) -> Self {
+        Self::Invalid(e)
+    }
+}
+
+impl From<sqlx::Error> for UserStoreError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => Self::Duplicate,
+            _ => Self::Backend(Box::new(e)),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for UserStoreError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => Self::Duplicate,
+            e => Self::Backend(Box::new(e)),
+        }
+    }
+}
+
+/// Minimal user persistence used by the checked helpers.
+/// Implementations store values as given; validation happens before they are called.
+pub trait UserStore {
+    fn create(&self, username: &str, email: &str) -> impl Future<Output = Result<StoredUser, UserStoreError>> + Send;
+    fn find_by_username(&self, username: &str) -> impl Future<Output = Result<Option<StoredUser>, UserStoreError>> + Send;
+    /// Returns whether a row was deleted.
+    fn delete(&self, id: i32) -> impl Future<Output = Result<bool, UserStoreError>> + Send;
+}
+
+/// Validates the username and canonicalizes the email, then creates the user in `store`.
+/// Why: One validation path whichever backend is configured.
+pub async fn create_user_checked(store: &impl UserStore, username: &str, email: &str) -> Result<StoredUser, UserStoreError> {
+    DEFAULT_VALIDATOR.validate(username)?;
+    let email = canonicalize_email(email)?;
+    store.create(username, &email).await
+}
+
+/// Validates the lookup key, then queries `store`.
+pub async fn find_user_checked(store: &impl UserStore, username: &str) -> Result<Option<StoredUser>, UserStoreError> {
+    DEFAULT_VALIDATOR.validate(username)?;
+    store.find_by_username(username).await
+}
+
+/// `UserStore` over a SQLx pool; creation goes through the `create_user` procedure.
+#[derive(Debug, Clone)]
+pub struct SqlxUserStore {
+    pool: sqlx::PgPool,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl SqlxUserStore {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool, breaker: None }
+    }
+
+    /// Routes every query through `breaker`, so an outage fails fast with `CircuitOpen`
+    /// instead of each call waiting out the pool timeout. Duplicates don't count as failures.
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+
+    async fn guarded<T>(&self, op: impl Future<Output = Result<T, UserStoreError>>) -> Result<T, UserStoreError> {
+        let Some(breaker) = &self.breaker else {
+            return op.await;
+        };
+        breaker
+            .call_filtered(op, |e| matches!(e, UserStoreError::Backend(_)))
+            .await
+            .map_err(|e| match e {
+                BreakerError::CircuitOpen { retry_after } => UserStoreError::CircuitOpen { retry_after },
+                BreakerError::Inner(e) => e,
+            })
+    }
+}
+
+impl UserStore for SqlxUserStore {
+    async fn create(&self, username: &str, email: &str) -> Result<StoredUser, UserStoreError> {
+        self.guarded(async {
+            let (id, username, email) = sqlx::query_as::<_, (i32, String, String)>(
+                "SELECT id, username, email FROM create_user($1, $2)",
+            )
+            .bind(username)
+            .bind(email)
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(StoredUser { id, username, email })
+        })
+        .await
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<StoredUser>, UserStoreError> {
+        self.guarded(async {
+            let row = sqlx::query_as::<_, (i32, String, String)>(
+                "SELECT id, username, email FROM users WHERE username = $1",
+            )
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?;
+            Ok(row.map(|(id, username, email)| StoredUser { id, username, email }))
+        })
+        .await
+    }
+
+    async fn delete(&self, id: i32) -> Result<bool, UserStoreError> {
+        self.guarded(async {
+            let result = sqlx::query("DELETE FROM users WHERE id = $1").bind(id).execute(&self.pool).await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+}
+
+/// `UserStore` over a single Diesel connection.
+/// Diesel is synchronous, so each call blocks the calling task until the query returns.
+pub struct DieselUserStore {
+    conn: Mutex<PgConnection>,
+}
+
+impl DieselUserStore {
+    pub fn new(conn: PgConnection) -> Self {
+        Self { conn: Mutex::new(conn) }
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&mut PgConnection) -> QueryResult<T>) -> Result<T, UserStoreError> {
+        let mut conn = self.conn.lock().map_err(|_| UserStoreError::Backend("connection mutex poisoned".into()))?;
+        f(&mut conn).map_err(Into::into)
+    }
+}
+
+impl UserStore for DieselUserStore {
+    fn create(&self, username: &str, email: &str) -> impl Future<Output = Result<StoredUser, UserStoreError>> + Send {
+        use crate::schema::users::dsl;
+        let result = self.with_conn(|conn| {
+            diesel::insert_into(dsl::users)
+                .values(crate::NewDieselUser { username, email })
+                .returning((dsl::id, dsl::username, dsl::email))
+                .get_result::<(i32, String, String)>(conn)
+        });
+        future::ready(result.map(|(id, username, email)| StoredUser { id, username, email }))
+    }
+
+    fn find_by_username(&self, username: &str) -> impl Future<Output = Result<Option<StoredUser>, UserStoreError>> + Send {
+        use crate::schema::users::dsl;
+        let result = self.with_conn(|conn| {
+            dsl::users
+                .filter(dsl::username.eq(username))
+                .select((dsl::id, dsl::username, dsl::email))
+                .first::<(i32, String, String)>(conn)
+                .optional()
+        });
+        future::ready(result.map(|row| row.map(|(id, username, email)| StoredUser { id, username, email })))
+    }
+
+    fn delete(&self, id: i32) -> impl Future<Output = Result<bool, UserStoreError>> + Send {
+        use crate::schema::users::dsl;
+        let result = self.with_conn(|conn| diesel::delete(dsl::users.filter(dsl::id.eq(id))).execute(conn));
+        future::ready(result.map(|deleted| deleted > 0))
+    }
+}
+
+/// In-process `UserStore` with the same uniqueness rules as the `users` table.
+#[derive(Debug, Default)]
+pub struct InMemoryUserStore {
+    state: Mutex<InMemoryState>,
+}
+
+#[derive(Debug, Default)]
+struct InMemoryState {
+    users: HashMap<i32, StoredUser>,
+    next_id: i32,
+}
+
+impl InMemoryUserStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl UserStore for InMemoryUserStore {
+    fn create(&self, username: &str, email: &str) -> impl Future<Output = Result<StoredUser, UserStoreError>> + Send {
+        let mut state = self.state.lock().unwrap();
+        let taken = state.users.values().any(|u| u.username == username || u.email == email);
+        let result = if taken {
+            Err(UserStoreError::Duplicate)
+        } else {
+            state.next_id += 1; // Ids start at 1, like SERIAL
+            let user = StoredUser { id: state.next_id, username: username.to_string(), email: email.to_string() };
+            state.users.insert(user.id, user.clone());
+            Ok(user)
+        };
+        future::ready(result)
+    }
+
+    fn find_by_username(&self, username: &str) -> impl Future<Output = Result<Option<StoredUser>, UserStoreError>> + Send {
+        let state = self.state.lock().unwrap();
+        future::ready(Ok(state.users.values().find(|u| u.username == username).cloned()))
+    }
+
+    fn delete(&self, id: i32) -> impl Future<Output = Result<bool, UserStoreError>> + Send {
+        future::ready(Ok(self.state.lock().unwrap().users.remove(&id).is_some()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sqlx_store_circuit_opens_when_db_unreachable() {
+        // A "database" that hangs up on every connection: two failures open the circuit,
+        // then calls fail with CircuitOpen without the pool dialing it again.
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dialed = Arc::new(AtomicUsize::new(0));
+        let counter = dialed.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                drop(socket);
+            }
+        });
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy(&format!("postgres://nobody@{addr}/none?sslmode=disable"))
+            .unwrap();
+        let breaker = Arc::new(CircuitBreaker::new(2, Duration::from_secs(30)));
+        let store = SqlxUserStore::new(pool).with_circuit_breaker(breaker.clone());
+
+        for _ in 0..2 {
+            assert!(matches!(store.find_by_username("alice").await, Err(UserStoreError::Backend(_))));
+        }
+        assert_eq!(breaker.state(), crate::breaker::CircuitState::Open);
+        let dialed_before = dialed.load(Ordering::SeqCst);
+        assert!(dialed_before > 0);
+        assert!(matches!(store.create("alice", "alice@example.com").await, Err(UserStoreError::CircuitOpen { .. })));
+        assert!(matches!(store.find_by_username("alice").await, Err(UserStoreError::CircuitOpen { .. })));
+        tokio::task::yield_now().await;
+        assert_eq!(dialed.load(Ordering::SeqCst), dialed_before);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_duplicate_detection() {
+        // A second user with the same username or email is refused.
+        let store = InMemoryUserStore::new();
+        let alice = create_user_checked(&store, "alice", "alice@example.com").await.unwrap();
+        assert_eq!(alice.id, 1);
+        assert!(matches!(
+            create_user_checked(&store, "alice", "other@example.com").await,
+            Err(UserStoreError::Duplicate)
+        ));
+        assert!(matches!(
+            create_user_checked(&store, "alice2", "alice@example.com").await,
+            Err(UserStoreError::Duplicate)
+        ));
+        assert!(matches!(
+            create_user_checked(&store, "alice3", " Alice@Example.COM ").await,
+            Err(UserStoreError::Duplicate)
+        ));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_validation_runs_first() {
+        // Injection payloads and blank fields never reach the store.
+        let store = InMemoryUserStore::new();
+        assert!(matches!(
+            create_user_checked(&store, "' OR 1=1;--", "x@example.com").await,
+            Err(UserStoreError::Invalid(ValidationError::SqlPatternDetected { .. }))
+        ));
+        assert!(matches!(
+            create_user_checked(&store, "bob", "  ").await,
+            Err(UserStoreError::Invalid(ValidationError::EmptyInput))
+        ));
+        assert!(matches!(
+            find_user_checked(&store, "x' OR '1'='1").await,
+            Err(UserStoreError::Invalid(_))
+        ));
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_find_and_delete() {
+        // Lookup and delete behave like the table does.
+        let store = InMemoryUserStore::new();
+        let carol = create_user_checked(&store, "carol", "carol@example.com").await.unwrap();
+        assert_eq!(find_user_checked(&store, "carol").await.unwrap(), Some(carol.clone()));
+        assert!(store.delete(carol.id).await.unwrap());
+        assert!(!store.delete(carol.id).await.unwrap());
+        assert_eq!(find_user_checked(&store, "carol").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    async fn test_sqlx_store_maps_unique_violation() {
+        // The Postgres unique constraint surfaces as the same Duplicate error.
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = sqlx::PgPool::connect(&db_url).await.unwrap();
+        crate::run_migrations(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE username = 'store_dave'").execute(&pool).await.unwrap();
+
+        let store = SqlxUserStore::new(pool);
+        let dave = create_user_checked(&store, "store_dave", "dave@example.com").await.unwrap();
+        assert!(matches!(
+            create_user_checked(&store, "store_dave", "dave2@example.com").await,
+            Err(UserStoreError::Duplicate)
+        ));
+        assert_eq!(find_user_checked(&store, "store_dave").await.unwrap(), Some(dave.clone()));
+        assert!(store.delete(dave.id).await.unwrap());
+    }
+}
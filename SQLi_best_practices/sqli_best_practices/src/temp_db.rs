@@ -0,0 +1,137 @@
+//! Throwaway Postgres databases for demos and integration tests.
+//!
+//! `with_temp_database` creates a uniquely named database, hands the closure a
+//! pool connected to it, and drops it afterwards, including when the closure
+//! panics. The name is built from a validated prefix plus random hex, so no
+//! caller-controlled text is ever spliced into `CREATE`/`DROP DATABASE`.
+
+use std::error::Error;
+use std::future::Future;
+
+use rand::Rng;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+use crate::{ValidationError, validate_identifier};
+
+// Random suffix: "_" plus 16 hex digits
+const SUFFIX_LEN: usize = 17;
+const MAX_DATABASE_NAME_LEN: usize = 63;
+
+/// Creates `<prefix>_<random hex>`, runs `f` with a pool to it, then drops it.
+/// Why: DDL can't take bind parameters, so the name must be safe by construction.
+/// `prefix` must be a plain lowercase identifier (`[a-z_][a-z0-9_]*`).
+/// Connections reuse `admin_pool`'s credentials, and the admin role must be allowed to create databases.
+pub async fn with_temp_database<F, Fut, T>(admin_pool: &PgPool, prefix: &str, f: F) -> Result<T, Box<dyn Error>>
+where
+    F: FnOnce(PgPool, String) -> Fut,
+    Fut: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let name = temp_database_name(prefix)?;
+    sqlx::query(&format!(r#"CREATE DATABASE "{}""#, name)).execute(admin_pool).await?;
+
+    let options = (*admin_pool.connect_options()).clone().database(&name);
+    let pool = match PgPoolOptions::new().max_connections(5).connect_with(options).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            drop_database(admin_pool, &name).await?;
+            return Err(e.into());
+        }
+    };
+
+    // Spawned so a panic in `f` surfaces as a JoinError instead of skipping cleanup
+    let outcome = tokio::spawn(f(pool.clone(), name.clone())).await;
+    pool.close().await;
+    drop_database(admin_pool, &name).await?;
+
+    match outcome {
+        Ok(value) => Ok(value),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Validated prefix + random suffix, within Postgres' 63-byte identifier limit
+fn temp_database_name(prefix: &str) -> Result<String, ValidationError> {
+    validate_identifier(prefix)?;
+    let lowercase = prefix.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+    if !lowercase || prefix.len() + SUFFIX_LEN > MAX_DATABASE_NAME_LEN {
+        return Err(ValidationError::InvalidIdentifier);
+    }
+    Ok(format!("{}_{:016x}", prefix, rand::thread_rng().r#gen::<u64>()))
+}
+
+async fn drop_database(admin_pool: &PgPool, name: &str) -> Result<(), sqlx::Error> {
+    // FORCE (Postgres 13+) ends any connection the closure leaked
+    sqlx::query(&format!(r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#, name))
+        .execute(admin_pool)
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn admin_pool() -> PgPool {
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        PgPool::connect(&db_url).await.expect("connect to admin database")
+    }
+
+    async fn database_exists(admin: &PgPool, name: &str) -> bool {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+            .bind(name)
+            .fetch_one(admin)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_unsafe_prefixes_rejected() {
+        // Anything but a short lowercase identifier is refused before any SQL runs.
+        for bad in ["demo; DROP DATABASE postgres", "demo\"x", "Demo", "public.demo", "", &"a".repeat(47)] {
+            assert!(temp_database_name(bad).is_err(), "{bad:?}");
+        }
+        let name = temp_database_name("sqli_demo").unwrap();
+        assert!(name.starts_with("sqli_demo_") && name.len() == "sqli_demo".len() + SUFFIX_LEN);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    async fn test_temp_database_created_used_and_dropped() {
+        // The closure gets a working database, which is gone once it returns.
+        let admin = admin_pool().await;
+        let name = with_temp_database(&admin, "sqli_tmp", |pool, name| async move {
+            sqlx::query("CREATE TABLE t (id INT)").execute(&pool).await.unwrap();
+            sqlx::query("INSERT INTO t VALUES (1), (2)").execute(&pool).await.unwrap();
+            let count: i64 = sqlx::query_scalar("SELECT count(*) FROM t").fetch_one(&pool).await.unwrap();
+            assert_eq!(count, 2);
+            let current: String = sqlx::query_scalar("SELECT current_database()").fetch_one(&pool).await.unwrap();
+            assert_eq!(current, name);
+            name
+        })
+        .await
+        .unwrap();
+        assert!(!database_exists(&admin, &name).await);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    async fn test_temp_database_dropped_after_panic() {
+        // A panicking closure still leaves no database behind, and the panic propagates.
+        let admin = admin_pool().await;
+        let task = tokio::spawn(async move {
+            let admin = admin_pool().await;
+            with_temp_database(&admin, "sqli_panic", |_pool, _name| async move { panic!("boom") })
+                .await
+                .map_err(|e| e.to_string())
+        });
+        assert!(task.await.unwrap_err().is_panic());
+        let leftovers: i64 = sqlx::query_scalar("SELECT count(*) FROM pg_database WHERE datname LIKE 'sqli_panic_%'")
+            .fetch_one(&admin)
+            .await
+            .unwrap();
+        assert_eq!(leftovers, 0);
+    }
+}
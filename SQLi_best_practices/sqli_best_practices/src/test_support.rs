@@ -0,0 +1,42 @@
+//! Helpers shared by the crate's unit tests.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use tracing_subscriber::layer::SubscriberExt;
+
+// Collects everything the fmt layer writes
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `emit` under a plain-text fmt subscriber and returns what it logged,
+/// one `LEVEL target: message fields` line per event (no timestamps).
+pub(crate) fn capture_logs(emit: impl FnOnce()) -> String {
+    let captured = Captured::default();
+    let sink = captured.clone();
+    let subscriber = tracing_subscriber::registry().with(
+        tracing_subscriber::fmt::layer().without_time().with_ansi(false).with_writer(move || sink.clone()),
+    );
+    tracing::subscriber::with_default(subscriber, emit);
+    let bytes = captured.0.lock().unwrap().clone();
+    String::from_utf8(bytes).unwrap()
+}
+
+/// The target of each line `capture_logs` returned, so tests can check how logs will be routed.
+pub(crate) fn log_targets(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|target| target.trim_end_matches(':'))
+        .collect()
+}
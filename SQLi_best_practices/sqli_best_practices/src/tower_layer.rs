@@ -0,0 +1,221 @@
+//! Tower adapter for the SQLi input checks, for Axum, Hyper and other Tower stacks.
+//!
+//! `SqliValidationLayer` is the framework-agnostic counterpart of `SqliGuard`:
+//! every query parameter, and every field of an `application/x-www-form-urlencoded`
+//! body or text field of a `multipart/form-data` one, goes through `validate_input`;
+//! any hit short-circuits with `400 Bad Request`. File parts are not scanned, and
+//! bodies of any other content type are passed through without being buffered.
+//! Requires the `tower` feature.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::{header, Request, Response, StatusCode};
+use http_body::Body;
+use http_body_util::{BodyExt, Either, Full, LengthLimitError, Limited};
+use tower::{Layer, Service};
+
+use crate::guard::is_form_urlencoded;
+use crate::multipart;
+use crate::validate_input;
+use crate::validator::DEFAULT_MAX_SCAN_BYTES;
+
+/// Layer that wraps a service in `SqliValidation`.
+#[derive(Debug, Clone, Copy)]
+pub struct SqliValidationLayer {
+    max_body_bytes: usize,
+}
+
+impl SqliValidationLayer {
+    pub fn new() -> Self {
+        Self { max_body_bytes: DEFAULT_MAX_SCAN_BYTES }
+    }
+
+    /// Form and multipart bodies larger than this are refused with 413 before being scanned.
+    pub fn max_body_bytes(mut self, max: usize) -> Self {
+        self.max_body_bytes = max;
+        self
+    }
+}
+
+impl Default for SqliValidationLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for SqliValidationLayer {
+    type Service = SqliValidation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SqliValidation { inner, max_body_bytes: self.max_body_bytes }
+    }
+}
+
+/// Service produced by `SqliValidationLayer`.
+/// Form and multipart bodies are buffered (up to the limit) so they can be scanned
+/// and reach the inner service as `Either::Left(Full<Bytes>)`; any other body is
+/// forwarded untouched as `Either::Right`.
+#[derive(Debug, Clone)]
+pub struct SqliValidation<S> {
+    inner: S,
+    max_body_bytes: usize,
+}
+
+impl<S, B, ResBody> Service<Request<B>> for SqliValidation<S>
+where
+    S: Service<Request<Either<Full<Bytes>, B>>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        // Take the service that was driven to readiness; leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let max_body_bytes = self.max_body_bytes;
+
+        Box::pin(async move {
+            if !query_is_clean(req.uri().query().unwrap_or("")) {
+                return Ok(reject(StatusCode::BAD_REQUEST));
+            }
+
+            let content_type = req.headers().get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("");
+            let is_form = is_form_urlencoded(content_type);
+            let is_multipart = multipart::is_multipart(content_type);
+            if !is_form && !is_multipart {
+                return inner.call(req.map(Either::Right)).await;
+            }
+            let boundary = multipart::boundary(content_type).map(str::to_string);
+            let (parts, body) = req.into_parts();
+            let bytes = match Limited::new(body, max_body_bytes).collect().await {
+                Ok(collected) => collected.to_bytes(),
+                Err(e) if e.is::<LengthLimitError>() => return Ok(reject(StatusCode::PAYLOAD_TOO_LARGE)),
+                Err(_) => return Ok(reject(StatusCode::BAD_REQUEST)),
+            };
+            if is_form && !query_is_clean(&String::from_utf8_lossy(&bytes)) {
+                return Ok(reject(StatusCode::BAD_REQUEST));
+            }
+            if is_multipart {
+                let clean = match boundary.map(|b| multipart::text_fields(&bytes, &b)) {
+                    Some(Ok(fields)) => fields.iter().all(|(_, value)| validate_input(value).is_ok()),
+                    _ => false,
+                };
+                if !clean {
+                    return Ok(reject(StatusCode::BAD_REQUEST));
+                }
+            }
+
+            inner.call(Request::from_parts(parts, Either::Left(Full::new(bytes)))).await
+        })
+    }
+}
+
+// Same verdict as the Actix guard: any failing value rejects the whole request
+fn query_is_clean(encoded: &str) -> bool {
+    form_urlencoded::parse(encoded.as_bytes()).all(|(_, value)| validate_input(&value).is_ok())
+}
+
+// Never echo the input back: the reason stays server-side
+fn reject<ResBody: Default>(status: StatusCode) -> Response<ResBody> {
+    tracing::warn!(target: "sqli", status = status.as_u16(), "Request rejected by SQLi validation layer");
+    let mut response = Response::new(ResBody::default());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    async fn echo(req: Request<Either<Full<Bytes>, Full<Bytes>>>) -> Result<Response<Full<Bytes>>, Infallible> {
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        Ok(Response::new(Full::new(body)))
+    }
+
+    fn get(uri: &str) -> Request<Full<Bytes>> {
+        Request::get(uri).body(Full::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_malicious_query_rejected_clean_passes() {
+        // A tautology in the query string never reaches the inner service.
+        let service = SqliValidationLayer::new().layer(service_fn(echo));
+        let resp = service.clone().oneshot(get("/users?name=%27%20OR%201%3D1--")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = service.oneshot(get("/users?name=alice&page=2")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_form_body_scanned_and_forwarded() {
+        // Form fields are checked too; a clean body still arrives intact.
+        let service = SqliValidationLayer::new().max_body_bytes(64).layer(service_fn(echo));
+        let form = |body: &str| {
+            Request::post("/users")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Full::new(Bytes::copy_from_slice(body.as_bytes())))
+                .unwrap()
+        };
+
+        let resp = service.clone().oneshot(form("username=x%27%3B%20DROP%20TABLE%20users")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = service.clone().oneshot(form("username=alice")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await.unwrap().to_bytes(), "username=alice");
+        let resp = service.oneshot(form(&format!("username={}", "a".repeat(64)))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_only_form_and_multipart_bodies_buffered() {
+        // A JSON body over the limit is forwarded as is; the form check ignores header case.
+        let service = SqliValidationLayer::new().max_body_bytes(64).layer(service_fn(echo));
+        let post = |content_type: &str, body: String| {
+            Request::post("/users")
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Full::new(Bytes::from(body)))
+                .unwrap()
+        };
+
+        let json = format!("{{\"bio\":\"{}\"}}", "a".repeat(128));
+        let resp = service.clone().oneshot(post("application/json", json.clone())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await.unwrap().to_bytes(), json);
+        let attack = "username=x%27%3B%20DROP%20TABLE%20users".to_string();
+        let resp = service.oneshot(post("Application/X-WWW-Form-Urlencoded", attack)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_text_field_scanned() {
+        // Text parts of an upload form are checked like form fields.
+        let service = SqliValidationLayer::new().layer(service_fn(echo));
+        let upload = |caption: &str| {
+            let body =
+                format!("--b\r\nContent-Disposition: form-data; name=\"caption\"\r\n\r\n{caption}\r\n--b--\r\n");
+            Request::post("/upload")
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+                .body(Full::new(Bytes::from(body)))
+                .unwrap()
+        };
+
+        let resp = service.clone().oneshot(upload("x' UNION SELECT password FROM users --")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = service.oneshot(upload("Holiday photos")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
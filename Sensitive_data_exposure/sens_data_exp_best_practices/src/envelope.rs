@@ -0,0 +1,349 @@
+// src/envelope.rs
+
+use std::fmt;
+use std::sync::Mutex;
+
+use chacha20poly1305::{
+    aead::{AeadCore, AeadInPlace, KeyInit, OsRng, Error as AeadError},
+    ChaCha20Poly1305, Key, Nonce, XChaCha20Poly1305, XNonce
+};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 32;
+const XNONCE_LEN: usize = 24;
+
+/// Current layout version written by `seal`.
+pub const ENVELOPE_VERSION: u8 = 1;
+const HEADER_LEN: usize = 2;
+
+/// Errors from the sealing helpers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    /// Blob is too short or otherwise not in the expected layout.
+    Malformed,
+    /// HMAC did not match: wrong key, tampered blob, or a blob belonging to another record.
+    InvalidSignature,
+    /// AEAD encryption or decryption failed.
+    Aead,
+    /// Header names a version or algorithm this build doesn't support.
+    UnsupportedHeader { version: u8, alg_id: u8 },
+    /// `NonceGuard` has (probably) seen this nonce before; nothing was encrypted.
+    NonceReuse,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "sealed data is malformed"),
+            Self::InvalidSignature => write!(f, "signature verification failed"),
+            Self::Aead => write!(f, "authenticated encryption failed"),
+            Self::UnsupportedHeader { version, alg_id } => {
+                write!(f, "unsupported envelope version {version} / algorithm {alg_id}")
+            }
+            Self::NonceReuse => write!(f, "nonce was already used under this guard"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+impl From<AeadError> for CryptoError {
+    fn from(_: AeadError) -> Self {
+        Self::Aead
+    }
+}
+
+/// AEAD algorithm recorded in a sealed envelope's header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Algorithm {
+    /// 12-byte random nonce; fine for moderate volumes per key.
+    ChaCha20Poly1305 = 1,
+    /// 24-byte random nonce; use for high-volume keys where 12-byte nonces could collide.
+    XChaCha20Poly1305 = 2,
+}
+
+impl Algorithm {
+    fn from_id(alg_id: u8) -> Option<Self> {
+        match alg_id {
+            1 => Some(Self::ChaCha20Poly1305),
+            2 => Some(Self::XChaCha20Poly1305),
+            _ => None,
+        }
+    }
+}
+
+/// Bloom filter of nonces already used in this process, consulted by `seal_guarded`.
+///
+/// Random nonces collide only through a broken RNG or a forked/cloned process
+/// state, but a single collision under ChaCha20-Poly1305 leaks the XOR of two
+/// plaintexts and lets an attacker forge tags. The guard turns that silent failure
+/// into `CryptoError::NonceReuse`.
+///
+/// Tradeoff: a bloom filter has no false negatives but does have false positives.
+/// Sized for `expected_nonces` at `false_positive_rate`, roughly that fraction of
+/// fresh nonces is wrongly refused (retrying `seal_guarded` draws a new nonce);
+/// past `expected_nonces` the rate climbs, so rotate the key and the guard together.
+/// Memory is about `-1.44 * log2(rate)` bits per expected nonce (~1.2 MB for 1M at 1e-6).
+#[derive(Debug)]
+pub struct NonceGuard {
+    bits: Mutex<Vec<u64>>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl NonceGuard {
+    pub fn new(expected_nonces: usize, false_positive_rate: f64) -> Self {
+        let n = expected_nonces.max(1) as f64;
+        let p = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let num_bits = (-n * p.ln() / std::f64::consts::LN_2.powi(2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * std::f64::consts::LN_2).round().max(1.0) as u32;
+        Self { bits: Mutex::new(vec![0; num_bits.div_ceil(64) as usize]), num_bits, num_hashes }
+    }
+
+    /// Records `nonce`; returns false if it was (probably) recorded before.
+    pub fn check_and_insert(&self, nonce: &[u8]) -> bool {
+        // Double hashing: bit i = h1 + i * h2, both taken from one SHA-256
+        let digest = Sha256::digest(nonce);
+        let h1 = u64::from_le_bytes(digest[..8].try_into().expect("8 bytes"));
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().expect("8 bytes")) | 1;
+
+        let mut bits = self.bits.lock().unwrap();
+        let mut seen = true;
+        for i in 0..self.num_hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            seen &= bits[word] & mask != 0;
+            bits[word] |= mask;
+        }
+        !seen
+    }
+}
+
+/// Encrypts `plaintext` with a fresh random nonce under the chosen algorithm.
+/// Output layout: `version (1) || alg_id (1) || nonce (12 or 24) || ciphertext+tag`.
+/// The header is authenticated as associated data, so it can't be rewritten to
+/// downgrade the algorithm.
+pub fn seal(key: &Key, alg: Algorithm, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    seal_with_nonce(key, alg, &random_nonce(alg), plaintext, None)
+}
+
+/// `seal`, refusing with `CryptoError::NonceReuse` if `guard` has seen the nonce.
+pub fn seal_guarded(key: &Key, alg: Algorithm, plaintext: &[u8], guard: &NonceGuard) -> Result<Vec<u8>, CryptoError> {
+    seal_with_nonce(key, alg, &random_nonce(alg), plaintext, Some(guard))
+}
+
+fn random_nonce(alg: Algorithm) -> Vec<u8> {
+    match alg {
+        Algorithm::ChaCha20Poly1305 => ChaCha20Poly1305::generate_nonce(&mut OsRng).to_vec(),
+        Algorithm::XChaCha20Poly1305 => XChaCha20Poly1305::generate_nonce(&mut OsRng).to_vec(),
+    }
+}
+
+fn seal_with_nonce(
+    key: &Key,
+    alg: Algorithm,
+    nonce: &[u8],
+    plaintext: &[u8],
+    guard: Option<&NonceGuard>,
+) -> Result<Vec<u8>, CryptoError> {
+    if guard.is_some_and(|guard| !guard.check_and_insert(nonce)) {
+        return Err(CryptoError::NonceReuse);
+    }
+    let header = [ENVELOPE_VERSION, alg as u8];
+    let mut buffer = plaintext.to_vec();
+    match alg {
+        Algorithm::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new(key).encrypt_in_place(Nonce::from_slice(nonce), &header, &mut buffer)?
+        }
+        Algorithm::XChaCha20Poly1305 => {
+            XChaCha20Poly1305::new(key).encrypt_in_place(XNonce::from_slice(nonce), &header, &mut buffer)?
+        }
+    }
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + nonce.len() + buffer.len());
+    sealed.extend_from_slice(&header);
+    sealed.extend_from_slice(nonce);
+    sealed.extend_from_slice(&buffer);
+    Ok(sealed)
+}
+
+/// Decrypts an envelope produced by `seal`, picking the algorithm from its header.
+pub fn open(key: &Key, sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let [version, alg_id, ..] = *sealed else {
+        return Err(CryptoError::Malformed);
+    };
+    let alg = Algorithm::from_id(alg_id)
+        .filter(|_| version == ENVELOPE_VERSION)
+        .ok_or(CryptoError::UnsupportedHeader { version, alg_id })?;
+    let (header, rest) = sealed.split_at(HEADER_LEN);
+
+    let nonce_len = match alg {
+        Algorithm::ChaCha20Poly1305 => NONCE_LEN,
+        Algorithm::XChaCha20Poly1305 => XNONCE_LEN,
+    };
+    if rest.len() < nonce_len {
+        return Err(CryptoError::Malformed);
+    }
+    let (nonce, ciphertext) = rest.split_at(nonce_len);
+    let mut buffer = ciphertext.to_vec();
+    match alg {
+        Algorithm::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new(key).decrypt_in_place(Nonce::from_slice(nonce), header, &mut buffer)?
+        }
+        Algorithm::XChaCha20Poly1305 => {
+            XChaCha20Poly1305::new(key).decrypt_in_place(XNonce::from_slice(nonce), header, &mut buffer)?
+        }
+    }
+    Ok(buffer)
+}
+
+/// Encrypts `plaintext`, then HMAC-signs it together with `record_id`.
+/// Binding the signature to the record id stops an attacker with write access from
+/// swapping two valid ciphertexts between records: each only verifies under its own id.
+/// Output layout: `nonce (12) || ciphertext+tag || hmac (32)`.
+pub fn seal_signed(
+    enc_key: &Key,
+    sign_key: &[u8],
+    record_id: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut ciphertext = plaintext.to_vec();
+    ChaCha20Poly1305::new(enc_key).encrypt_in_place(&nonce, b"", &mut ciphertext)?;
+
+    let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len() + TAG_LEN);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    let signature = record_mac(sign_key, record_id, &sealed).finalize().into_bytes();
+    sealed.extend_from_slice(&signature);
+    Ok(sealed)
+}
+
+/// Verifies the signature for the expected `record_id` (in constant time), then decrypts.
+/// Nothing is decrypted unless the blob was sealed for exactly this record.
+pub fn open_signed(
+    enc_key: &Key,
+    sign_key: &[u8],
+    record_id: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    if sealed.len() < NONCE_LEN + TAG_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let (body, signature) = sealed.split_at(sealed.len() - TAG_LEN);
+    record_mac(sign_key, record_id, body)
+        .verify_slice(signature)
+        .map_err(|_| CryptoError::InvalidSignature)?;
+
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    let mut buffer = ciphertext.to_vec();
+    ChaCha20Poly1305::new(enc_key).decrypt_in_place(Nonce::from_slice(nonce), b"", &mut buffer)?;
+    Ok(buffer)
+}
+
+// MAC over len(record_id) || record_id || body; the length prefix keeps
+// ("ab", "c...") and ("a", "bc...") from producing the same input
+fn record_mac(sign_key: &[u8], record_id: &[u8], body: &[u8]) -> HmacSha256 {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(sign_key).expect("HMAC accepts keys of any length");
+    mac.update(&(record_id.len() as u64).to_be_bytes());
+    mac.update(record_id);
+    mac.update(body);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_round_trip() {
+        // A blob opens under the record id it was sealed for.
+        let enc_key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let sealed = seal_signed(&enc_key, b"sign-key", b"user:42", b"ssn=123-45-6789").unwrap();
+        let opened = open_signed(&enc_key, b"sign-key", b"user:42", &sealed).unwrap();
+        assert_eq!(opened, b"ssn=123-45-6789");
+    }
+
+    #[test]
+    fn test_swapped_ciphertext_fails_signature() {
+        // Moving a valid blob to another record is detected before decryption.
+        let enc_key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let alice = seal_signed(&enc_key, b"sign-key", b"user:1", b"alice-secret").unwrap();
+        assert_eq!(
+            open_signed(&enc_key, b"sign-key", b"user:2", &alice),
+            Err(CryptoError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_tampered_or_short_blob() {
+        // Bit flips and truncated input are rejected, never decrypted.
+        let enc_key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let mut sealed = seal_signed(&enc_key, b"sign-key", b"user:1", b"data").unwrap();
+        sealed[NONCE_LEN] ^= 0x01;
+        assert_eq!(
+            open_signed(&enc_key, b"sign-key", b"user:1", &sealed),
+            Err(CryptoError::InvalidSignature)
+        );
+        assert_eq!(
+            open_signed(&enc_key, b"sign-key", b"user:1", &[0u8; 10]),
+            Err(CryptoError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_seal_open_both_algorithms() {
+        // The header's alg_id selects the cipher; XChaCha envelopes carry a 24-byte nonce.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let classic = seal(&key, Algorithm::ChaCha20Poly1305, b"payload").unwrap();
+        let extended = seal(&key, Algorithm::XChaCha20Poly1305, b"payload").unwrap();
+        assert_eq!(&classic[..2], &[ENVELOPE_VERSION, 1]);
+        assert_eq!(&extended[..2], &[ENVELOPE_VERSION, 2]);
+        assert_eq!(extended.len() - classic.len(), XNONCE_LEN - NONCE_LEN);
+        assert_eq!(open(&key, &classic).unwrap(), b"payload");
+        assert_eq!(open(&key, &extended).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_seal_header_tampering() {
+        // Rewriting the alg_id or body fails; unknown headers are reported as such.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let sealed = seal(&key, Algorithm::XChaCha20Poly1305, b"payload").unwrap();
+
+        let mut body_flip = sealed.clone();
+        *body_flip.last_mut().unwrap() ^= 0x01;
+        assert_eq!(open(&key, &body_flip), Err(CryptoError::Aead));
+
+        let mut downgraded = sealed.clone();
+        downgraded[1] = Algorithm::ChaCha20Poly1305 as u8;
+        assert!(open(&key, &downgraded).is_err());
+
+        let mut unknown = sealed;
+        unknown[1] = 9;
+        assert_eq!(open(&key, &unknown), Err(CryptoError::UnsupportedHeader { version: 1, alg_id: 9 }));
+        assert_eq!(open(&key, &[1]), Err(CryptoError::Malformed));
+    }
+
+    #[test]
+    fn test_nonce_guard_catches_reuse() {
+        // Feeding the same nonce twice is refused before encrypting; fresh nonces pass.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let guard = NonceGuard::new(1_000, 1e-6);
+        let nonce = random_nonce(Algorithm::ChaCha20Poly1305);
+        assert!(seal_with_nonce(&key, Algorithm::ChaCha20Poly1305, &nonce, b"first", Some(&guard)).is_ok());
+        assert_eq!(
+            seal_with_nonce(&key, Algorithm::ChaCha20Poly1305, &nonce, b"second", Some(&guard)),
+            Err(CryptoError::NonceReuse)
+        );
+
+        for _ in 0..100 {
+            let sealed = seal_guarded(&key, Algorithm::XChaCha20Poly1305, b"payload", &guard).unwrap();
+            assert_eq!(open(&key, &sealed).unwrap(), b"payload");
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// src/keyring.rs
+
+use std::fmt;
+
+use chacha20poly1305::Key;
+
+use crate::envelope::{self, Algorithm, CryptoError};
+
+/// Ordered set of envelope keys for rotation, newest first.
+/// New data is sealed under the newest key; `open_with_ring` also accepts blobs sealed
+/// under any older key still on the ring, so rotation needs no big-bang re-encryption.
+/// Drop a key from the ring only once nothing sealed under it remains.
+#[derive(Clone)]
+pub struct KeyRing {
+    keys: Vec<(String, Key)>,
+}
+
+impl KeyRing {
+    /// A ring holding only `key`, which becomes the newest.
+    pub fn new(id: impl Into<String>, key: Key) -> Self {
+        Self { keys: vec![(id.into(), key)] }
+    }
+
+    /// Adds an older key behind the ones already on the ring.
+    pub fn with_older(mut self, id: impl Into<String>, key: Key) -> Self {
+        self.keys.push((id.into(), key));
+        self
+    }
+
+    /// Id of the key new data is sealed under.
+    pub fn newest_id(&self) -> &str {
+        &self.keys[0].0
+    }
+
+    /// Key ids, newest first.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(|(id, _)| id.as_str())
+    }
+
+    /// Seals `plaintext` under the newest key (`envelope::seal`).
+    pub fn seal(&self, alg: Algorithm, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        envelope::seal(&self.keys[0].1, alg, plaintext)
+    }
+}
+
+impl fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Ids only; the derived impl would print key bytes
+        f.debug_struct("KeyRing").field("ids", &self.ids().collect::<Vec<_>>()).finish()
+    }
+}
+
+/// Plaintext from `open_with_ring`, with the id of the key that authenticated it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opened {
+    pub plaintext: Vec<u8>,
+    pub key_id: String,
+}
+
+impl Opened {
+    /// True if the blob was sealed under an older key and should be re-sealed with `KeyRing::seal`.
+    pub fn needs_reseal(&self, ring: &KeyRing) -> bool {
+        self.key_id != ring.newest_id()
+    }
+}
+
+/// Opens an `envelope::seal` blob with the first ring key that authenticates it, newest first.
+/// Fails with `CryptoError::Aead` once every key has been tried; header errors fail
+/// straight away, since no key can fix them.
+pub fn open_with_ring(ring: &KeyRing, sealed: &[u8]) -> Result<Opened, CryptoError> {
+    for (id, key) in &ring.keys {
+        match envelope::open(key, sealed) {
+            Ok(plaintext) => return Ok(Opened { plaintext, key_id: id.clone() }),
+            Err(CryptoError::Aead) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(CryptoError::Aead)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chacha20poly1305::aead::{KeyInit, OsRng};
+    use chacha20poly1305::ChaCha20Poly1305;
+
+    fn ring() -> (KeyRing, Key) {
+        let old = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let ring = KeyRing::new("2025-06", ChaCha20Poly1305::generate_key(&mut OsRng))
+            .with_older("2025-01", old)
+            .with_older("2024-06", ChaCha20Poly1305::generate_key(&mut OsRng));
+        (ring, old)
+    }
+
+    #[test]
+    fn test_older_key_still_opens_and_asks_for_reseal() {
+        // A blob from before the rotation opens under its key; re-sealing moves it to the newest.
+        let (ring, old) = ring();
+        let sealed = envelope::seal(&old, Algorithm::XChaCha20Poly1305, b"card 4111").unwrap();
+        let opened = open_with_ring(&ring, &sealed).unwrap();
+        assert_eq!(opened, Opened { plaintext: b"card 4111".to_vec(), key_id: "2025-01".into() });
+        assert!(opened.needs_reseal(&ring));
+
+        let resealed = ring.seal(Algorithm::XChaCha20Poly1305, &opened.plaintext).unwrap();
+        let reopened = open_with_ring(&ring, &resealed).unwrap();
+        assert_eq!(reopened.key_id, "2025-06");
+        assert!(!reopened.needs_reseal(&ring));
+    }
+
+    #[test]
+    fn test_unknown_key_fails_after_all_tried() {
+        // A blob from a key not on the ring fails authentication; Debug never shows key bytes.
+        let (ring, _) = ring();
+        let stranger = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let sealed = envelope::seal(&stranger, Algorithm::ChaCha20Poly1305, b"x").unwrap();
+        assert_eq!(open_with_ring(&ring, &sealed), Err(CryptoError::Aead));
+        let bad_header = open_with_ring(&ring, b"\x09\x01short");
+        assert_eq!(bad_header, Err(CryptoError::UnsupportedHeader { version: 9, alg_id: 1 }));
+        assert_eq!(format!("{:?}", ring), r#"KeyRing { ids: ["2025-06", "2025-01", "2024-06"] }"#);
+    }
+}
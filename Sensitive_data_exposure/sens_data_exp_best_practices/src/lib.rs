@@ -0,0 +1,248 @@
+// src/secrets.rs
+
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Sha256, Digest};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chacha20poly1305::{
+    aead::{AeadInPlace, KeyInit, Error as AeadError},
+    ChaCha20Poly1305, Key, Nonce, XChaCha20Poly1305, XNonce
+};
+
+pub mod demo;
+pub mod entropy;
+pub mod envelope;
+pub mod keyring;
+pub mod password;
+pub mod redaction;
+pub mod reset;
+pub mod secure_field;
+#[cfg(feature = "serde")]
+pub mod serde_secret;
+pub mod tenant;
+pub mod test_vectors;
+
+/// Wraps a sensitive string in a secure container.
+/// Prevents accidental leaks (e.g., via logs) and ensures memory is wiped on drop.
+/// Use this for API keys, tokens, or any secrets handled in memory.
+pub fn create_secret(data: &str) -> SecretString {
+    SecretString::new(data.to_owned().into())
+}
+
+/// Computes the SHA-256 hash of input data.
+/// Useful for data integrity checks, fingerprinting, or storing non-reversible identifiers.
+/// Do not use for password storage—use bcrypt for that.
+pub fn hash_data(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+/// Computes HMAC-SHA256 of `data` keyed by a secret pepper.
+/// Use instead of `hash_data` for low-entropy identifiers (emails, phone numbers) in
+/// deduplication indexes: without the pepper, the outputs can't be precomputed.
+pub fn fingerprint(data: &[u8], pepper: &SecretString) -> Vec<u8> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(pepper.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Hashes a password using bcrypt, which includes a random salt and work factor.
+/// Protects user passwords against brute-force and rainbow table attacks.
+/// Store only the resulting hash, never the plaintext password.
+pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
+    hash(password, DEFAULT_COST)
+}
+
+/// Verifies a plaintext password against a bcrypt hash using constant-time comparison.
+/// Prevents timing attacks and ensures only valid credentials are accepted.
+/// Returns Ok(true) if the password matches, Ok(false) if not, or an error if the hash is invalid.
+pub fn verify_password(password: &str, hashed: &str) -> Result<bool, bcrypt::BcryptError> {
+    verify(password, hashed)
+}
+
+/// `hash_password` on tokio's blocking thread pool; use this from async handlers.
+/// bcrypt is deliberately slow CPU work, and running it inline stalls every other task on that worker.
+pub async fn hash_password_async(password: &str) -> Result<String, bcrypt::BcryptError> {
+    let password = create_secret(password);
+    run_blocking(move || hash_password(password.expose_secret())).await
+}
+
+/// `verify_password` on tokio's blocking thread pool; use this from async handlers.
+/// Must be called inside a tokio runtime.
+pub async fn verify_password_async(password: &str, hashed: &str) -> Result<bool, bcrypt::BcryptError> {
+    let password = create_secret(password);
+    let hashed = hashed.to_owned();
+    run_blocking(move || verify_password(password.expose_secret(), &hashed)).await
+}
+
+// A panic in the closure is re-raised in the caller, as if it had run inline
+async fn run_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// Encrypts data using the ChaCha20-Poly1305 AEAD cipher for confidentiality and authenticity.
+/// Requires a unique key and nonce for each encryption to prevent replay and nonce reuse attacks.
+/// Returns ciphertext that includes an authentication tag to detect tampering.
+pub fn encrypt_secret(
+    key: &Key,
+    nonce: &Nonce,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, AeadError> {
+    let cipher = ChaCha20Poly1305::new(key);
+    let mut buffer = plaintext.to_vec();
+    cipher.encrypt_in_place(nonce, b"", &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Decrypts data encrypted by `encrypt_secret`, verifying its authenticity.
+/// If the ciphertext or authentication tag is tampered, decryption fails.
+/// Returns the original plaintext if successful, or an error if verification fails.
+pub fn decrypt_secret(
+    key: &Key,
+    nonce: &Nonce,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, AeadError> {
+    let cipher = ChaCha20Poly1305::new(key);
+    let mut buffer = ciphertext.to_vec();
+    cipher.decrypt_in_place(nonce, b"", &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Encrypts data with XChaCha20-Poly1305, which takes a 24-byte nonce.
+/// The larger nonce makes random nonces safe even across billions of messages,
+/// where ChaCha20-Poly1305's 12-byte nonce would risk a collision.
+pub fn encrypt_secret_xchacha(
+    key: &Key,
+    nonce: &XNonce,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, AeadError> {
+    let cipher = XChaCha20Poly1305::new(key);
+    let mut buffer = plaintext.to_vec();
+    cipher.encrypt_in_place(nonce, b"", &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Decrypts data encrypted by `encrypt_secret_xchacha`, verifying its authenticity.
+pub fn decrypt_secret_xchacha(
+    key: &Key,
+    nonce: &XNonce,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, AeadError> {
+    let cipher = XChaCha20Poly1305::new(key);
+    let mut buffer = ciphertext.to_vec();
+    cipher.decrypt_in_place(nonce, b"", &mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chacha20poly1305::aead::{AeadCore, OsRng};
+
+    #[test]
+    fn test_secret_handling() {
+        // Ensures secrets are wrapped and exposed correctly.
+        let secret = create_secret("confidential");
+        assert_eq!(secret.expose_secret(), "confidential");
+    }
+
+    #[test]
+    fn test_encryption_and_decryption() {
+        // Checks round-trip encryption and decryption for data integrity.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = b"supersecret";
+        let ciphertext = encrypt_secret(&key, &nonce, plaintext).unwrap();
+        let decrypted = decrypt_secret(&key, &nonce, &ciphertext).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_password_workflow() {
+        // Verifies password hashing and authentication logic.
+        let password = "Str0ngP@ssw0rd!";
+        let hash = hash_password(password).unwrap();
+        assert!(verify_password(password, &hash).unwrap());
+        assert!(!verify_password("wrong", &hash).unwrap());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_async_verify_does_not_stall_runtime() {
+        // On a single-threaded runtime, a spawned task completes while verification is still pending.
+        let hash = hash_password_async("Str0ngP@ssw0rd!").await.unwrap();
+        let verify = verify_password_async("Str0ngP@ssw0rd!", &hash);
+        tokio::pin!(verify);
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move { done_tx.send(()).unwrap() });
+        // Polling verify first: if bcrypt ran inline, the task could not run before it finished
+        tokio::select! {
+            biased;
+            _ = &mut verify => panic!("verification blocked the runtime thread"),
+            done = done_rx => done.unwrap(),
+        }
+        assert!(verify.await.unwrap());
+        assert!(!verify_password_async("wrong", &hash).await.unwrap());
+    }
+
+    #[test]
+    fn test_sha256_known_value() {
+        // Confirms SHA-256 hashing produces expected output.
+        let data = b"hello world";
+        let hash = hash_data(data);
+        assert_eq!(
+            hex::encode(hash),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_is_peppered() {
+        // Same input and pepper agree; another pepper, or plain SHA-256, does not.
+        let pepper = create_secret("pepper-one");
+        let email = b"alice@example.com";
+        assert_eq!(fingerprint(email, &pepper), fingerprint(email, &pepper));
+        assert_ne!(fingerprint(email, &pepper), fingerprint(email, &create_secret("pepper-two")));
+        assert_ne!(fingerprint(email, &pepper), hash_data(email));
+        assert_eq!(fingerprint(email, &pepper).len(), 32);
+    }
+
+    #[test]
+    fn test_fingerprint_pepper_not_logged() {
+        // Logging the pepper by mistake prints a placeholder, and the output doesn't embed it.
+        let pepper = create_secret("pepper-one");
+        assert!(!format!("{:?}", pepper).contains("pepper-one"));
+        assert!(!hex::encode(fingerprint(b"alice@example.com", &pepper)).contains(&hex::encode("pepper-one")));
+    }
+
+    #[test]
+    fn test_tampered_ciphertext() {
+        // Ensures tampered ciphertext fails authentication.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut ciphertext = encrypt_secret(&key, &nonce, b"valid").unwrap();
+        ciphertext[0] ^= 0x01;
+        assert!(decrypt_secret(&key, &nonce, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_xchacha_round_trip_and_nonce_length() {
+        // XChaCha uses a 24-byte nonce and round-trips like the 12-byte variant.
+        let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        assert_eq!(nonce.len(), 24);
+        let ciphertext = encrypt_secret_xchacha(&key, &nonce, b"bulk record").unwrap();
+        assert_eq!(decrypt_secret_xchacha(&key, &nonce, &ciphertext).unwrap(), b"bulk record");
+    }
+
+    #[test]
+    fn test_xchacha_tampered_ciphertext() {
+        // A flipped bit fails authentication under XChaCha too.
+        let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut ciphertext = encrypt_secret_xchacha(&key, &nonce, b"valid").unwrap();
+        ciphertext[0] ^= 0x01;
+        assert!(decrypt_secret_xchacha(&key, &nonce, &ciphertext).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+// src/password.rs
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Version,
+};
+
+pub use argon2::Params as Argon2Params;
+
+use crate::entropy::estimate_entropy_bits;
+
+// Search bounds for `tune_argon2_params`, in KiB and passes
+const MIN_TUNE_M_COST: u32 = 1024;
+const MAX_TUNE_M_COST: u32 = 1024 * 1024;
+const MAX_TUNE_T_COST: u32 = 8;
+
+/// Selects the algorithm used for new password hashes.
+/// Switching the configured variant only affects hashing: `verify` detects the stored
+/// format itself, so existing bcrypt hashes keep working after a move to argon2.
+#[derive(Debug, Clone)]
+pub enum PasswordHasher {
+    Bcrypt { cost: u32 },
+    Argon2 { params: Argon2Params },
+}
+
+/// Errors from hashing or verifying through `PasswordHasher`.
+#[derive(Debug)]
+pub enum PasswordHashError {
+    Bcrypt(bcrypt::BcryptError),
+    Argon2(argon2::password_hash::Error),
+    /// The stored hash is neither a bcrypt nor an argon2 PHC string.
+    UnknownFormat,
+}
+
+impl fmt::Display for PasswordHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bcrypt(e) => write!(f, "bcrypt error: {e}"),
+            Self::Argon2(e) => write!(f, "argon2 error: {e}"),
+            Self::UnknownFormat => write!(f, "unrecognized password hash format"),
+        }
+    }
+}
+
+impl std::error::Error for PasswordHashError {}
+
+impl From<bcrypt::BcryptError> for PasswordHashError {
+    fn from(e: bcrypt::BcryptError) -> Self {
+        Self::Bcrypt(e)
+    }
+}
+
+impl From<argon2::password_hash::Error> for PasswordHashError {
+    fn from(e: argon2::password_hash::Error) -> Self {
+        Self::Argon2(e)
+    }
+}
+
+impl PasswordHasher {
+    /// Hashes a password with the configured algorithm and a fresh random salt.
+    /// The result is self-describing (bcrypt `$2b$...` or PHC `$argon2id$...`).
+    pub fn hash(&self, password: &str) -> Result<String, PasswordHashError> {
+        match self {
+            Self::Bcrypt { cost } => Ok(bcrypt::hash(password, *cost)?),
+            Self::Argon2 { params } => {
+                let salt = SaltString::generate(&mut OsRng);
+                let hash = argon2_with(params).hash_password(password.as_bytes(), &salt)?;
+                Ok(hash.to_string())
+            }
+        }
+    }
+
+    /// Verifies a password against a stored hash of either format, in constant time.
+    /// Returns Ok(false) on mismatch and an error only if the hash itself is unusable.
+    pub fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordHashError> {
+        if is_bcrypt_hash(hash) {
+            Ok(bcrypt::verify(password, hash)?)
+        } else if hash.starts_with("$argon2") {
+            // Parameters are read from the PHC string, not from our config
+            let parsed = PasswordHash::new(hash)?;
+            match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+                Ok(()) => Ok(true),
+                Err(argon2::password_hash::Error::Password) => Ok(false),
+                Err(e) => Err(e.into()),
+            }
+        } else {
+            Err(PasswordHashError::UnknownFormat)
+        }
+    }
+}
+
+impl Default for PasswordHasher {
+    fn default() -> Self {
+        Self::Bcrypt { cost: bcrypt::DEFAULT_COST }
+    }
+}
+
+/// Picks argon2id parameters that take at least `target` for one hash on this machine.
+/// Memory is doubled first (from 1 MiB, up to 1 GiB), then passes are added (up to 8);
+/// the first setting that reaches the target is returned, or the largest one tried.
+/// Run this at deploy time, not per request: each probe is a full hash.
+pub fn tune_argon2_params(target: Duration) -> Argon2Params {
+    tune_with(target, time_one_hash)
+}
+
+// The search itself, with the stopwatch passed in so tests can use a fake cost model
+fn tune_with(target: Duration, mut time: impl FnMut(&Argon2Params) -> Duration) -> Argon2Params {
+    let mut m_cost = MIN_TUNE_M_COST;
+    let mut t_cost = 1;
+    loop {
+        let params = Argon2Params::new(m_cost, t_cost, 1, None).expect("tuning bounds are legal argon2 params");
+        if time(&params) >= target {
+            return params;
+        }
+        if m_cost < MAX_TUNE_M_COST {
+            m_cost *= 2;
+        } else if t_cost < MAX_TUNE_T_COST {
+            t_cost += 1;
+        } else {
+            return params; // Search space exhausted; this is the strongest we tried
+        }
+    }
+}
+
+fn time_one_hash(params: &Argon2Params) -> Duration {
+    let mut out = [0u8; 32];
+    let started = Instant::now();
+    argon2_with(params)
+        .hash_password_into(b"tuning-probe", b"tuning-salt-0000", &mut out)
+        .expect("probe inputs are valid");
+    started.elapsed()
+}
+
+fn argon2_with(params: &Argon2Params) -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone())
+}
+
+/// Reads the cost factor from a bcrypt hash such as `$2b$12$<53 chars of salt+hash>`.
+/// Fails with `UnknownFormat` for anything that isn't a well-formed bcrypt string.
+pub fn extract_bcrypt_cost(hash: &str) -> Result<u32, PasswordHashError> {
+    if !is_bcrypt_hash(hash) {
+        return Err(PasswordHashError::UnknownFormat);
+    }
+    // "$2b$" prefix, two cost digits, "$", then 22 salt + 31 hash characters
+    match hash[4..].split_once('$') {
+        Some((cost, rest)) if cost.len() == 2 && rest.len() == 53 => cost
+            .parse::<u32>()
+            .ok()
+            .filter(|cost| (4..=31).contains(cost))
+            .ok_or(PasswordHashError::UnknownFormat),
+        _ => Err(PasswordHashError::UnknownFormat),
+    }
+}
+
+/// Result of `audit_hash_costs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AuditSummary {
+    pub total: usize,
+    /// bcrypt hashes whose cost is below the threshold; rehash on next login.
+    pub below_threshold: usize,
+    /// Hashes that aren't bcrypt (e.g. argon2) or are malformed; not counted either way.
+    pub unparsed: usize,
+}
+
+/// Counts stored bcrypt hashes still below `min_acceptable` cost.
+pub fn audit_hash_costs(hashes: &[String], min_acceptable: u32) -> AuditSummary {
+    let mut summary = AuditSummary { total: hashes.len(), ..Default::default() };
+    for hash in hashes {
+        match extract_bcrypt_cost(hash) {
+            Ok(cost) if cost < min_acceptable => summary.below_threshold += 1,
+            Ok(_) => {}
+            Err(_) => summary.unparsed += 1,
+        }
+    }
+    summary
+}
+
+/// The new password matches one of the user's previous password hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordReused;
+
+impl fmt::Display for PasswordReused {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "password was used recently; choose a different one")
+    }
+}
+
+impl std::error::Error for PasswordReused {}
+
+/// Rejects `new_password` if it verifies against any of `previous_hashes` (bcrypt or argon2,
+/// detected per hash). Every hash is checked even after a match, so the time taken
+/// doesn't reveal which history entry matched. Unreadable hashes are skipped with a warning.
+pub fn check_password_history(new_password: &str, previous_hashes: &[String]) -> Result<(), PasswordReused> {
+    let verifier = PasswordHasher::default();
+    let mut reused = false;
+    for (index, hash) in previous_hashes.iter().enumerate() {
+        match verifier.verify(new_password, hash) {
+            Ok(matched) => reused |= matched,
+            Err(e) => tracing::warn!(target: "auth", index, error = %e, "Skipping unreadable password history entry"),
+        }
+    }
+    if reused { Err(PasswordReused) } else { Ok(()) }
+}
+
+/// Minimum `estimate_entropy_bits` score accepted by `check_password_strength`.
+pub const MIN_PASSWORD_ENTROPY_BITS: f64 = 40.0;
+
+/// The password's estimated entropy is below `MIN_PASSWORD_ENTROPY_BITS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeakPassword {
+    pub estimated_bits: u32,
+}
+
+impl fmt::Display for WeakPassword {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "password is too weak (about {} bits of entropy)", self.estimated_bits)
+    }
+}
+
+impl std::error::Error for WeakPassword {}
+
+/// Rejects passwords whose `estimate_entropy_bits` score is below `MIN_PASSWORD_ENTROPY_BITS`.
+/// Uses the same estimator as `looks_like_secret`, so anything the log redactor would treat
+/// as a random key also passes here. Pair with a breached-password list for real deployments.
+pub fn check_password_strength(password: &str) -> Result<(), WeakPassword> {
+    let bits = estimate_entropy_bits(password);
+    if bits < MIN_PASSWORD_ENTROPY_BITS { Err(WeakPassword { estimated_bits: bits as u32 }) } else { Ok(()) }
+}
+
+fn is_bcrypt_hash(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters keep the tests fast; never use these in production.
+    fn fast_argon2() -> PasswordHasher {
+        PasswordHasher::Argon2 { params: Argon2Params::new(8 * 1024, 1, 1, None).unwrap() }
+    }
+
+    #[test]
+    fn test_hash_uses_configured_algorithm() {
+        // Each variant produces its own self-describing hash format.
+        let bcrypt_hash = PasswordHasher::Bcrypt { cost: 4 }.hash("pw").unwrap();
+        assert!(bcrypt_hash.starts_with("$2b$04$"));
+        let argon_hash = fast_argon2().hash("pw").unwrap();
+        assert!(argon_hash.starts_with("$argon2id$"));
+    }
+
+    #[test]
+    fn test_verify_across_algorithms() {
+        // After switching to argon2, old bcrypt hashes still verify (and vice versa).
+        let old_hash = PasswordHasher::Bcrypt { cost: 4 }.hash("Str0ngP@ss").unwrap();
+        let new_hash = fast_argon2().hash("Str0ngP@ss").unwrap();
+
+        let argon = fast_argon2();
+        assert!(argon.verify("Str0ngP@ss", &old_hash).unwrap());
+        assert!(!argon.verify("wrong", &old_hash).unwrap());
+
+        let bcrypt = PasswordHasher::Bcrypt { cost: 4 };
+        assert!(bcrypt.verify("Str0ngP@ss", &new_hash).unwrap());
+        assert!(!bcrypt.verify("wrong", &new_hash).unwrap());
+    }
+
+    #[test]
+    fn test_password_history_rejects_reuse() {
+        // A password from either hash format in the history is refused; a new one passes.
+        let history = vec![
+            PasswordHasher::Bcrypt { cost: 4 }.hash("Winter2023!").unwrap(),
+            fast_argon2().hash("Spring2024!").unwrap(),
+            "not-a-hash".to_string(),
+        ];
+        assert_eq!(check_password_history("Winter2023!", &history), Err(PasswordReused));
+        assert_eq!(check_password_history("Spring2024!", &history), Err(PasswordReused));
+        assert_eq!(check_password_history("Autumn2025!", &history), Ok(()));
+        assert_eq!(check_password_history("anything", &[]), Ok(()));
+    }
+
+    #[test]
+    fn test_unknown_hash_format() {
+        // Plaintext or unknown formats are an error, never a match.
+        assert!(matches!(
+            PasswordHasher::default().verify("pw", "pw"),
+            Err(PasswordHashError::UnknownFormat)
+        ));
+    }
+
+    // Published bcrypt test vectors at costs 5 and 10, plus a cost-12 hash
+    const COST_5: &str = "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW";
+    const COST_10: &str = "$2b$10$N9qo8uLOickgx2ZMRZoMyeIjZAgcfl7p92ldGxad68LJZdL17lhWy";
+    const COST_12: &str = "$2y$12$R9h/cIPz0gi.URNNX3kh2OPST9/PgBkqquzi.Ss7KIUgO2t0jWMUW";
+
+    #[test]
+    fn test_extract_bcrypt_cost() {
+        // The cost is read from any bcrypt prefix; other formats are refused.
+        assert_eq!(extract_bcrypt_cost(COST_5).unwrap(), 5);
+        assert_eq!(extract_bcrypt_cost(COST_10).unwrap(), 10);
+        assert_eq!(extract_bcrypt_cost(COST_12).unwrap(), 12);
+        for bad in ["$2b$1$short", "$2b$xx$N9qo8uLOickgx2ZMRZoMyeIjZAgcfl7p92ldGxad68LJZdL17lhWy", "$argon2id$v=19$m=8192,t=1,p=1$c2FsdA$aGFzaA", "pw"] {
+            assert!(matches!(extract_bcrypt_cost(bad), Err(PasswordHashError::UnknownFormat)), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_audit_hash_costs_mixed_batch() {
+        // Only parseable bcrypt hashes under the threshold are flagged.
+        let hashes = [COST_5, COST_10, COST_12, "$argon2id$v=19$m=8192,t=1,p=1$c2FsdA$aGFzaA"].map(String::from);
+        assert_eq!(audit_hash_costs(&hashes, 12), AuditSummary { total: 4, below_threshold: 2, unparsed: 1 });
+        assert_eq!(audit_hash_costs(&hashes, 4).below_threshold, 0);
+    }
+
+    #[test]
+    fn test_strength_agrees_with_secret_detection() {
+        // Random keys pass the strength check and are redacted; weak passwords fail both.
+        use crate::redaction::looks_like_secret;
+        let random = "9fK2xQ7pLm4ZtR8vB1nW6yH3cJ5dG0sA";
+        assert!(check_password_strength(random).is_ok());
+        assert!(looks_like_secret(random));
+        assert!(check_password_strength("Str0ngP@ssw0rd!").is_ok());
+
+        for weak in ["password", "aaaaaaaaaaaaaaaaaaaaaaaaaaaa", "12345678"] {
+            assert!(check_password_strength(weak).is_err(), "{weak}");
+            assert!(!looks_like_secret(weak), "{weak}");
+        }
+        assert!(check_password_strength("password").unwrap_err().estimated_bits < 25);
+    }
+
+    #[test]
+    fn test_tune_argon2_params_picks_first_setting_over_target() {
+        // With a fake cost of 1ms per MiB-pass, memory doubles first, then passes are added, then the search stops.
+        let fake = |params: &Argon2Params| Duration::from_millis(u64::from(params.m_cost() / 1024 * params.t_cost()));
+        let tuned = |target_ms| {
+            let params = tune_with(Duration::from_millis(target_ms), fake);
+            (params.m_cost(), params.t_cost(), params.p_cost())
+        };
+        assert_eq!(tuned(0), (MIN_TUNE_M_COST, 1, 1));
+        assert_eq!(tuned(16), (16 * 1024, 1, 1));
+        assert_eq!(tuned(20), (32 * 1024, 1, 1));
+        assert_eq!(tuned(2000), (MAX_TUNE_M_COST, 2, 1));
+        assert_eq!(tuned(1_000_000), (MAX_TUNE_M_COST, MAX_TUNE_T_COST, 1));
+    }
+
+    #[test]
+    fn test_tuned_params_hash_and_verify() {
+        // The real stopwatch path returns params argon2 accepts and the default hasher can verify.
+        let params = tune_argon2_params(Duration::ZERO);
+        let hash = PasswordHasher::Argon2 { params }.hash("pw").unwrap();
+        assert!(PasswordHasher::default().verify("pw", &hash).unwrap());
+    }
+}
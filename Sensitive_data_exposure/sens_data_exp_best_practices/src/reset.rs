@@ -0,0 +1,228 @@
+// src/reset.rs
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Domain separation: a MAC minted for another purpose never verifies as a reset token
+const TOKEN_PURPOSE: &[u8] = b"password-reset-v1";
+const NONCE_LEN: usize = 16;
+
+/// Reasons a reset token is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResetTokenError {
+    /// Not in the `user.expiry.nonce.mac` shape we issue.
+    Malformed,
+    /// Tampered with, or issued under another key.
+    InvalidSignature,
+    /// Past its expiry time.
+    Expired,
+    /// Already redeemed once.
+    AlreadyUsed,
+}
+
+impl fmt::Display for ResetTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed reset token"),
+            Self::InvalidSignature => write!(f, "invalid reset token signature"),
+            Self::Expired => write!(f, "reset token has expired"),
+            Self::AlreadyUsed => write!(f, "reset token has already been used"),
+        }
+    }
+}
+
+impl std::error::Error for ResetTokenError {}
+
+/// What a valid reset token vouches for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResetClaim {
+    pub user_id: String,
+    /// Unix seconds after which the token is refused.
+    pub expires_at: u64,
+}
+
+/// Remembers redeemed tokens so each works once.
+/// Back it with a unique-keyed table or Redis `SET NX` when running more than one node.
+pub trait UsedTokenStore {
+    /// Records `token_id` until `expires_at`; returns false if it was already recorded.
+    fn mark_used(&self, token_id: &str, expires_at: u64) -> bool;
+}
+
+/// Process-local `UsedTokenStore`; entries are dropped once their token has expired.
+#[derive(Debug, Default)]
+pub struct InMemoryUsedTokens {
+    used: Mutex<HashMap<String, u64>>,
+}
+
+impl InMemoryUsedTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UsedTokenStore for InMemoryUsedTokens {
+    fn mark_used(&self, token_id: &str, expires_at: u64) -> bool {
+        let now = unix_now();
+        let mut used = self.used.lock().unwrap();
+        used.retain(|_, exp| *exp >= now); // Expired tokens fail verification anyway
+        used.insert(token_id.to_string(), expires_at).is_none()
+    }
+}
+
+/// Issues a reset token for `user_id`, valid for `ttl`.
+/// Format: hex(user_id) "." expiry-unix-seconds "." hex(random nonce) "." hex(HMAC-SHA256).
+/// The nonce makes every token unique, so two resets for the same user can be told apart.
+pub fn issue_reset_token(user_id: &str, key: &[u8], ttl: Duration) -> String {
+    issue_reset_token_at(user_id, key, ttl, unix_now())
+}
+
+/// `issue_reset_token` as if the current time were `now` (Unix seconds).
+pub fn issue_reset_token_at(user_id: &str, key: &[u8], ttl: Duration, now: u64) -> String {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let expires_at = now + ttl.as_secs();
+    let mac = sign(key, user_id.as_bytes(), expires_at, &nonce).finalize().into_bytes();
+    format!("{}.{}.{}.{}", hex::encode(user_id), expires_at, hex::encode(nonce), hex::encode(mac))
+}
+
+/// Checks the signature (in constant time), then the expiry.
+/// Stateless: use `redeem_reset_token` when actually changing the password.
+pub fn verify_reset_token(token: &str, key: &[u8]) -> Result<ResetClaim, ResetTokenError> {
+    verify_reset_token_at(token, key, unix_now())
+}
+
+/// `verify_reset_token` as if the current time were `now` (Unix seconds).
+pub fn verify_reset_token_at(token: &str, key: &[u8], now: u64) -> Result<ResetClaim, ResetTokenError> {
+    let parsed = parse_token(token)?;
+    sign(key, &parsed.user_id, parsed.expires_at, &parsed.nonce)
+        .verify_slice(&parsed.mac)
+        .map_err(|_| ResetTokenError::InvalidSignature)?;
+
+    if now > parsed.expires_at {
+        return Err(ResetTokenError::Expired);
+    }
+    let user_id = String::from_utf8(parsed.user_id).map_err(|_| ResetTokenError::Malformed)?;
+    Ok(ResetClaim { user_id, expires_at: parsed.expires_at })
+}
+
+/// Verifies the token, then records it in `used` so a second redemption fails.
+/// Only authenticated, unexpired tokens are recorded, so junk can't fill the store.
+pub fn redeem_reset_token(token: &str, key: &[u8], used: &impl UsedTokenStore) -> Result<ResetClaim, ResetTokenError> {
+    redeem_reset_token_at(token, key, used, unix_now())
+}
+
+/// `redeem_reset_token` as if the current time were `now` (Unix seconds).
+pub fn redeem_reset_token_at(
+    token: &str,
+    key: &[u8],
+    used: &impl UsedTokenStore,
+    now: u64,
+) -> Result<ResetClaim, ResetTokenError> {
+    let claim = verify_reset_token_at(token, key, now)?;
+    let nonce = parse_token(token)?.nonce;
+    if !used.mark_used(&hex::encode(nonce), claim.expires_at) {
+        return Err(ResetTokenError::AlreadyUsed);
+    }
+    Ok(claim)
+}
+
+struct ParsedToken {
+    user_id: Vec<u8>,
+    expires_at: u64,
+    nonce: Vec<u8>,
+    mac: Vec<u8>,
+}
+
+fn parse_token(token: &str) -> Result<ParsedToken, ResetTokenError> {
+    let mut parts = token.split('.');
+    let (Some(user_id), Some(expires_at), Some(nonce), Some(mac), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ResetTokenError::Malformed);
+    };
+    let decode = |part: &str| hex::decode(part).map_err(|_| ResetTokenError::Malformed);
+    let nonce = decode(nonce)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(ResetTokenError::Malformed);
+    }
+    Ok(ParsedToken {
+        user_id: decode(user_id)?,
+        expires_at: expires_at.parse().map_err(|_| ResetTokenError::Malformed)?,
+        nonce,
+        mac: decode(mac)?,
+    })
+}
+
+fn sign(key: &[u8], user_id: &[u8], expires_at: u64, nonce: &[u8]) -> HmacSha256 {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(TOKEN_PURPOSE);
+    // Length-prefix the variable field so (user, expiry) pairs can't be re-split
+    mac.update(&(user_id.len() as u64).to_be_bytes());
+    mac.update(user_id);
+    mac.update(&expires_at.to_be_bytes());
+    mac.update(nonce);
+    mac
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"reset-token-test-key-0123456789a";
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_valid_token_returns_user() {
+        // A fresh token yields the user it was issued for.
+        let token = issue_reset_token("user-42", KEY, Duration::from_secs(900));
+        let claim = verify_reset_token(&token, KEY).unwrap();
+        assert_eq!(claim.user_id, "user-42");
+        assert_ne!(token, issue_reset_token("user-42", KEY, Duration::from_secs(900)));
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        // The token works up to its expiry second and not after.
+        let token = issue_reset_token_at("user-42", KEY, Duration::from_secs(900), NOW);
+        assert!(verify_reset_token_at(&token, KEY, NOW + 900).is_ok());
+        assert_eq!(verify_reset_token_at(&token, KEY, NOW + 901), Err(ResetTokenError::Expired));
+    }
+
+    #[test]
+    fn test_tampered_token_rejected() {
+        // Changing the user or expiry, or verifying under another key, breaks the MAC.
+        let token = issue_reset_token_at("user-42", KEY, Duration::from_secs(900), NOW);
+        let parts: Vec<&str> = token.split('.').collect();
+
+        let other_user = format!("{}.{}.{}.{}", hex::encode("admin"), parts[1], parts[2], parts[3]);
+        assert_eq!(verify_reset_token_at(&other_user, KEY, NOW), Err(ResetTokenError::InvalidSignature));
+        let extended = format!("{}.{}.{}.{}", parts[0], u64::MAX, parts[2], parts[3]);
+        assert_eq!(verify_reset_token_at(&extended, KEY, NOW), Err(ResetTokenError::InvalidSignature));
+        assert_eq!(verify_reset_token_at(&token, b"other-key", NOW), Err(ResetTokenError::InvalidSignature));
+        assert_eq!(verify_reset_token_at("not-a-token", KEY, NOW), Err(ResetTokenError::Malformed));
+    }
+
+    #[test]
+    fn test_replayed_token_rejected() {
+        // Redeeming the same token twice fails; a second token for the same user still works.
+        let used = InMemoryUsedTokens::new();
+        let first = issue_reset_token("user-42", KEY, Duration::from_secs(900));
+        assert_eq!(redeem_reset_token(&first, KEY, &used).unwrap().user_id, "user-42");
+        assert_eq!(redeem_reset_token(&first, KEY, &used), Err(ResetTokenError::AlreadyUsed));
+
+        let second = issue_reset_token("user-42", KEY, Duration::from_secs(900));
+        assert!(redeem_reset_token(&second, KEY, &used).is_ok());
+    }
+}
@@ -0,0 +1,184 @@
+// src/secure_field.rs
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use chacha20poly1305::Key;
+use secrecy::SecretString;
+
+use crate::envelope::{self, Algorithm, CryptoError};
+use crate::redaction::REDACTED;
+
+/// Input rule for a `SecureField`, checked before anything is encrypted.
+pub trait FieldValidator {
+    /// Field kind shown in `Debug` output and errors, e.g. "email".
+    const NAME: &'static str;
+    /// Returns a fixed reason on failure; never include `value` in it.
+    fn validate(value: &str) -> Result<(), &'static str>;
+}
+
+/// Encryption at rest for a `SecureField`.
+pub trait FieldCipher {
+    fn seal(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError>;
+    fn open(key: &Key, sealed: &[u8]) -> Result<Vec<u8>, CryptoError>;
+}
+
+/// `local@domain` with a dotted domain and no whitespace.
+#[derive(Debug, Clone, Copy)]
+pub struct Email;
+
+impl FieldValidator for Email {
+    const NAME: &'static str = "email";
+
+    fn validate(value: &str) -> Result<(), &'static str> {
+        let (local, domain) = value.rsplit_once('@').ok_or("missing @")?;
+        if local.is_empty() || !domain.contains('.') || domain.starts_with('.') || domain.ends_with('.') {
+            return Err("not a valid email address");
+        }
+        if value.chars().any(char::is_whitespace) || value.len() > 254 {
+            return Err("not a valid email address");
+        }
+        Ok(())
+    }
+}
+
+/// Any value with at least one non-whitespace character.
+#[derive(Debug, Clone, Copy)]
+pub struct NonBlank;
+
+impl FieldValidator for NonBlank {
+    const NAME: &'static str = "value";
+
+    fn validate(value: &str) -> Result<(), &'static str> {
+        if value.trim().is_empty() { Err("must not be blank") } else { Ok(()) }
+    }
+}
+
+/// Versioned XChaCha20-Poly1305 envelope (`envelope::seal`); the default cipher.
+#[derive(Debug, Clone, Copy)]
+pub struct XChaChaEnvelope;
+
+impl FieldCipher for XChaChaEnvelope {
+    fn seal(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        envelope::seal(key, Algorithm::XChaCha20Poly1305, plaintext)
+    }
+
+    fn open(key: &Key, sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        envelope::open(key, sealed)
+    }
+}
+
+/// Errors from building or decrypting a `SecureField`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecureFieldError {
+    /// The plaintext failed the field's validator; nothing was encrypted.
+    Invalid { field: &'static str, reason: &'static str },
+    Crypto(CryptoError),
+    /// Decrypted bytes are not UTF-8, so they can't be a field we sealed.
+    NotUtf8,
+}
+
+impl fmt::Display for SecureFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Invalid { field, reason } => write!(f, "invalid {field}: {reason}"),
+            Self::Crypto(e) => write!(f, "{e}"),
+            Self::NotUtf8 => write!(f, "decrypted field is not valid UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for SecureFieldError {}
+
+impl From<CryptoError> for SecureFieldError {
+    fn from(e: CryptoError) -> Self {
+        Self::Crypto(e)
+    }
+}
+
+/// A sensitive value that is validated on input, held only as ciphertext, and
+/// redacted in `Debug`. Persist `as_sealed()`; reload with `from_sealed`.
+/// Example: `SecureField::<Email>::from_plaintext("a@example.com", &key)?`
+pub struct SecureField<V: FieldValidator, C: FieldCipher = XChaChaEnvelope> {
+    sealed: Vec<u8>,
+    _marker: PhantomData<(V, C)>,
+}
+
+impl<V: FieldValidator, C: FieldCipher> SecureField<V, C> {
+    /// Validates `plaintext` with `V`, then encrypts it with `C` under `key`.
+    pub fn from_plaintext(plaintext: &str, key: &Key) -> Result<Self, SecureFieldError> {
+        V::validate(plaintext).map_err(|reason| SecureFieldError::Invalid { field: V::NAME, reason })?;
+        Ok(Self::from_sealed(C::seal(key, plaintext.as_bytes())?))
+    }
+
+    /// Wraps ciphertext loaded from storage; it is authenticated on `decrypt`.
+    pub fn from_sealed(sealed: Vec<u8>) -> Self {
+        Self { sealed, _marker: PhantomData }
+    }
+
+    pub fn as_sealed(&self) -> &[u8] {
+        &self.sealed
+    }
+
+    /// Decrypts into a `SecretString`; fails on a wrong key or tampered ciphertext.
+    pub fn decrypt(&self, key: &Key) -> Result<SecretString, SecureFieldError> {
+        let plaintext = C::open(key, &self.sealed)?;
+        let plaintext = String::from_utf8(plaintext).map_err(|_| SecureFieldError::NotUtf8)?;
+        Ok(SecretString::from(plaintext))
+    }
+}
+
+impl<V: FieldValidator, C: FieldCipher> Clone for SecureField<V, C> {
+    fn clone(&self) -> Self {
+        Self::from_sealed(self.sealed.clone())
+    }
+}
+
+impl<V: FieldValidator, C: FieldCipher> fmt::Debug for SecureField<V, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Neither plaintext nor ciphertext: even ciphertext length hints at the value
+        write!(f, "SecureField<{}>({})", V::NAME, REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chacha20poly1305::aead::{KeyInit, OsRng};
+    use chacha20poly1305::ChaCha20Poly1305;
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn test_invalid_value_rejected_before_encryption() {
+        // A bad email never gets sealed, and the error doesn't repeat it.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let err = SecureField::<Email>::from_plaintext("alice at example", &key).unwrap_err();
+        assert_eq!(err, SecureFieldError::Invalid { field: "email", reason: "missing @" });
+        assert!(!err.to_string().contains("alice"));
+        assert!(SecureField::<NonBlank>::from_plaintext("   ", &key).is_err());
+    }
+
+    #[test]
+    fn test_round_trip_and_wrong_key() {
+        // The sealed value decrypts under its key only, also after a storage round trip.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let field = SecureField::<Email>::from_plaintext("alice@example.com", &key).unwrap();
+        assert!(!field.as_sealed().windows(5).any(|w| w == b"alice"));
+        assert_eq!(field.decrypt(&key).unwrap().expose_secret(), "alice@example.com");
+
+        let reloaded = SecureField::<Email>::from_sealed(field.as_sealed().to_vec());
+        assert_eq!(reloaded.decrypt(&key).unwrap().expose_secret(), "alice@example.com");
+        let other = ChaCha20Poly1305::generate_key(&mut OsRng);
+        assert_eq!(reloaded.decrypt(&other).unwrap_err(), SecureFieldError::Crypto(CryptoError::Aead));
+    }
+
+    #[test]
+    fn test_debug_is_redacted() {
+        // Debug names the field kind but shows neither plaintext nor ciphertext.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let field = SecureField::<Email>::from_plaintext("alice@example.com", &key).unwrap();
+        let debug = format!("{:?}", field);
+        assert_eq!(debug, "SecureField<email>(***)");
+        assert!(!debug.contains("alice"));
+    }
+}
@@ -0,0 +1,47 @@
+// src/serde_secret.rs
+
+use secrecy::SecretString;
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::redaction::REDACTED;
+
+/// Writes `REDACTED` in place of the secret.
+/// Use on a field with `#[serde(with = "sens_data_exp_best_practices::serde_secret")]`.
+/// A serialized config is a redacted dump, not a backup: reading it back yields `***`.
+pub fn serialize<S: Serializer>(_secret: &SecretString, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+/// Reads a string straight into a `SecretString`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SecretString, D::Error> {
+    String::deserialize(deserializer).map(SecretString::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::{ExposeSecret, SecretString};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct DbConfig {
+        host: String,
+        #[serde(with = "crate::serde_secret")]
+        password: SecretString,
+    }
+
+    #[test]
+    fn test_serialize_redacts_secret() {
+        // Dumping a config shows the shape but not the password.
+        let config = DbConfig { host: "db.internal".into(), password: SecretString::from("hunter2") };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(json, r#"{"host":"db.internal","password":"***"}"#);
+    }
+
+    #[test]
+    fn test_deserialize_populates_secret() {
+        // Loading a config fills the secret with the real value.
+        let config: DbConfig = serde_json::from_str(r#"{"host":"db.internal","password":"hunter2"}"#).unwrap();
+        assert_eq!(config.password.expose_secret(), "hunter2");
+        assert!(!serde_json::to_string(&config).unwrap().contains("hunter2"));
+    }
+}
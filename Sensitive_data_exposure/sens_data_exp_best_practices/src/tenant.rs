@@ -0,0 +1,64 @@
+// src/tenant.rs
+
+use chacha20poly1305::Key;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+// HKDF salt: separates tenant keys from anything else derived from the same master key
+const TENANT_KEY_SALT: &[u8] = b"sens_data_exp_best_practices/tenant-key/v1";
+
+/// The tenant a request acts for. Resolve it once (from the host, token, ...) and
+/// pass it down, so every key and policy lookup uses the same id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TenantContext {
+    pub id: String,
+}
+
+impl TenantContext {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into() }
+    }
+
+    /// This tenant's data key; see `derive_tenant_key`.
+    pub fn key(&self, master: &Key) -> Key {
+        derive_tenant_key(master, &self.id)
+    }
+}
+
+/// Derives a per-tenant 256-bit key from `master` with HKDF-SHA256 (RFC 5869),
+/// using the tenant id as `info`. Keys are deterministic, so nothing per-tenant
+/// needs storing, and a blob sealed for one tenant fails authentication under
+/// any other tenant's key. Rotating `master` rotates every tenant at once.
+pub fn derive_tenant_key(master: &Key, tenant_id: &str) -> Key {
+    let hkdf = Hkdf::<Sha256>::new(Some(TENANT_KEY_SALT), master);
+    let mut key = Key::default();
+    hkdf.expand(tenant_id.as_bytes(), &mut key).expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::{open, seal, Algorithm, CryptoError};
+    use chacha20poly1305::aead::{KeyInit, OsRng};
+    use chacha20poly1305::ChaCha20Poly1305;
+
+    #[test]
+    fn test_blob_for_one_tenant_fails_under_another() {
+        // Tenant A's ciphertext opens only with A's derived key.
+        let master = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let (acme, globex) = (TenantContext::new("acme"), TenantContext::new("globex"));
+        let sealed = seal(&acme.key(&master), Algorithm::XChaCha20Poly1305, b"acme payroll").unwrap();
+
+        assert_eq!(open(&acme.key(&master), &sealed).unwrap(), b"acme payroll");
+        assert_eq!(open(&globex.key(&master), &sealed), Err(CryptoError::Aead));
+        assert_ne!(acme.key(&master), master);
+    }
+
+    #[test]
+    fn test_derivation_matches_reference_hkdf() {
+        // Known answer computed independently (Python `cryptography` HKDF-SHA256, same salt and info).
+        let key = derive_tenant_key(&Key::default(), "acme");
+        assert_eq!(hex::encode(key), "71a134606f5c3a0feebcf0ceadc4a52e8ae287c4b4399154899415a76751ed7a");
+    }
+}
@@ -0,0 +1,119 @@
+// src/test_vectors.rs
+
+/// A fixed ChaCha20-Poly1305 input/output tuple, hex-encoded.
+/// `ciphertext` is the encrypted body followed by the 16-byte tag, which is the
+/// layout `encrypt_secret` returns and `decrypt_secret` expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    pub name: &'static str,
+    pub key: &'static str,
+    pub nonce: &'static str,
+    pub aad: &'static str,
+    pub plaintext: &'static str,
+    pub ciphertext: &'static str,
+}
+
+impl TestVector {
+    pub fn key_bytes(&self) -> Vec<u8> {
+        decode(self.key)
+    }
+
+    pub fn nonce_bytes(&self) -> Vec<u8> {
+        decode(self.nonce)
+    }
+
+    pub fn aad_bytes(&self) -> Vec<u8> {
+        decode(self.aad)
+    }
+
+    pub fn plaintext_bytes(&self) -> Vec<u8> {
+        decode(self.plaintext)
+    }
+
+    pub fn ciphertext_bytes(&self) -> Vec<u8> {
+        decode(self.ciphertext)
+    }
+}
+
+fn decode(hex_str: &str) -> Vec<u8> {
+    hex::decode(hex_str).expect("test vectors are valid hex")
+}
+
+// "Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it."
+const SUNSCREEN: &str = concat!(
+    "4c616469657320616e642047656e746c656d656e206f662074686520636c6173",
+    "73206f66202739393a204966204920636f756c64206f6666657220796f75206f",
+    "6e6c79206f6e652074697020666f7220746865206675747572652c2073756e73",
+    "637265656e20776f756c642062652069742e",
+);
+
+/// ChaCha20-Poly1305 vectors. The first is RFC 8439 section 2.8.2; the others use an
+/// empty AAD, as `encrypt_secret` does, and were cross-checked against OpenSSL.
+pub const CHACHA20_POLY1305: [TestVector; 3] = [
+    TestVector {
+        name: "rfc8439-2.8.2",
+        key: "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+        nonce: "070000004041424344454647",
+        aad: "50515253c0c1c2c3c4c5c6c7",
+        plaintext: SUNSCREEN,
+        ciphertext: concat!(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+            "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+            "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+            "3ff4def08e4b7a9de576d26586cec64b61161ae10b594f09e26a7e902ecbd0600691",
+        ),
+    },
+    TestVector {
+        name: "rfc8439-key-empty-aad",
+        key: "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+        nonce: "070000004041424344454647",
+        aad: "",
+        plaintext: SUNSCREEN,
+        ciphertext: concat!(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+            "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+            "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+            "3ff4def08e4b7a9de576d26586cec64b61166a23a4681fd59456aea1d29f82477216",
+        ),
+    },
+    TestVector {
+        name: "zero-key-empty-message",
+        key: "0000000000000000000000000000000000000000000000000000000000000000",
+        nonce: "000000000000000000000000",
+        aad: "",
+        plaintext: "",
+        ciphertext: "4eb972c9a8fb3a1b382bb4d36f5ffad1",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt_secret, encrypt_secret};
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+    #[test]
+    fn test_encrypt_secret_matches_vectors() {
+        // encrypt_secret reproduces each empty-AAD ciphertext byte for byte, and decrypt_secret inverts it.
+        for vector in CHACHA20_POLY1305.iter().filter(|v| v.aad.is_empty()) {
+            let key = Key::from_slice(&vector.key_bytes()).to_owned();
+            let nonce = Nonce::from_slice(&vector.nonce_bytes()).to_owned();
+            let ciphertext = encrypt_secret(&key, &nonce, &vector.plaintext_bytes()).unwrap();
+            assert_eq!(hex::encode(&ciphertext), vector.ciphertext, "{}", vector.name);
+            assert_eq!(decrypt_secret(&key, &nonce, &ciphertext).unwrap(), vector.plaintext_bytes(), "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_rfc8439_vector_with_aad() {
+        // The underlying cipher matches the RFC's published AEAD example, AAD included.
+        let vector = &CHACHA20_POLY1305[0];
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&vector.key_bytes()));
+        let nonce = vector.nonce_bytes();
+        let payload = Payload { msg: &vector.plaintext_bytes(), aad: &vector.aad_bytes() };
+        let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), payload).unwrap();
+        assert_eq!(hex::encode(ciphertext), vector.ciphertext);
+        assert_eq!(vector.plaintext_bytes().len() + 16, vector.ciphertext_bytes().len());
+    }
+}